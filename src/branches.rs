@@ -0,0 +1,191 @@
+use chrono::{DateTime, Utc};
+use colored::*;
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::models::UsageEntry;
+use crate::projects::{prefer_exact_path, project_key};
+use crate::report::{ReportZone, UsageSummary};
+
+/// Shown for usage outside a git repository
+const NO_BRANCH: &str = "(no branch)";
+
+/// Usage of one branch of one project
+#[derive(Debug, Serialize)]
+pub struct BranchUsage {
+    pub project: String,
+    pub branch: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    #[serde(flatten)]
+    pub usage: UsageSummary,
+}
+
+/// Usage grouped by project and git branch, most expensive first
+pub fn build_branches<'a>(entries: impl IntoIterator<Item = &'a UsageEntry>) -> Vec<BranchUsage> {
+    let mut branches: HashMap<(&str, &str), BranchUsage> = HashMap::new();
+    // One label per project, shared by all of its branches
+    let mut labels: HashMap<&str, String> = HashMap::new();
+    
+    for entry in entries {
+        let key = project_key(entry);
+        let label = labels.entry(key).or_insert_with(|| entry.project_path().to_string());
+        prefer_exact_path(label, entry);
+        
+        let branch = entry.git_branch.as_deref().unwrap_or(NO_BRANCH);
+        let row = branches.entry((key, branch)).or_insert_with(|| BranchUsage {
+            project: String::new(),
+            branch: branch.to_string(),
+            first_seen: entry.timestamp,
+            last_seen: entry.timestamp,
+            usage: UsageSummary::default(),
+        });
+        row.first_seen = row.first_seen.min(entry.timestamp);
+        row.last_seen = row.last_seen.max(entry.timestamp);
+        row.usage.add(entry);
+    }
+    
+    let mut branches: Vec<BranchUsage> = branches
+        .into_iter()
+        .map(|((key, _), row)| BranchUsage {
+            project: labels[key].clone(),
+            ..row
+        })
+        .collect();
+    branches.sort_by(|a, b| {
+        b.usage
+            .total_cost
+            .total_cmp(&a.usage.total_cost)
+            .then(b.usage.total_tokens.cmp(&a.usage.total_tokens))
+            .then(a.project.cmp(&b.project))
+            .then(a.branch.cmp(&b.branch))
+    });
+    branches
+}
+
+pub fn print_branches(branches: &[BranchUsage], scope: &str, zone: &ReportZone) {
+    println!("{}", "Claude Code Usage - Branches".bold().green());
+    println!("Scope: {}\n", scope);
+    
+    if branches.is_empty() {
+        println!("{}", "No usage in the selected range".yellow());
+        return;
+    }
+    
+    let project_width = branches.iter().map(|b| b.project.len()).chain(["Project".len()]).max().unwrap_or(7);
+    let branch_width = branches.iter().map(|b| b.branch.len()).chain(["Branch".len()]).max().unwrap_or(6);
+    println!(
+        "{}",
+        format!(
+            "{:<project_width$}  {:<branch_width$} {:>9} {:>15} {:>11}  Last used",
+            "Project", "Branch", "Requests", "Tokens", "Cost",
+            project_width = project_width,
+            branch_width = branch_width
+        )
+        .bold()
+    );
+    
+    for row in branches {
+        let branch = if row.branch == NO_BRANCH {
+            row.branch.dimmed()
+        } else {
+            row.branch.cyan()
+        };
+        println!(
+            "{:<project_width$}  {:<branch_width$} {:>9} {:>15} {:>11}  {}",
+            row.project,
+            branch,
+            row.usage.entry_count.to_formatted_string(&Locale::en),
+            row.usage.total_tokens.to_formatted_string(&Locale::en),
+            format!("${:.2}", row.usage.total_cost),
+            zone.format(row.last_seen, "%Y-%m-%d %H:%M"),
+            project_width = project_width,
+            branch_width = branch_width
+        );
+    }
+}
+
+/// The branches as CSV, one row per project and branch
+pub fn branches_csv(branches: &[BranchUsage]) -> String {
+    let mut csv = String::from(
+        "project,branch,requests,input_tokens,output_tokens,cache_creation_tokens,cache_read_tokens,total_tokens,cost_usd,first_seen,last_seen\n",
+    );
+    for row in branches {
+        let fields = [
+            csv_field(&row.project),
+            csv_field(&row.branch),
+            row.usage.entry_count.to_string(),
+            row.usage.input_tokens.to_string(),
+            row.usage.output_tokens.to_string(),
+            row.usage.cache_creation_tokens.to_string(),
+            row.usage.cache_read_tokens.to_string(),
+            row.usage.total_tokens.to_string(),
+            format!("{:.6}", row.usage.total_cost),
+            row.first_seen.to_rfc3339(),
+            row.last_seen.to_rfc3339(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quote a CSV field when it contains a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_branches_grouped_and_exported() {
+        let main = UsageEntry {
+            project: Some("/repo".to_string()),
+            git_branch: Some("main".to_string()),
+            total_tokens: 100,
+            cost: 1.0,
+            ..UsageEntry::test_at("2024-01-01T10:00:00Z")
+        };
+        let feature = UsageEntry {
+            git_branch: Some("feat/x,y".to_string()),
+            cost: 2.0,
+            ..main.clone()
+        };
+        let entries = [
+            main.clone(),
+            feature.clone(),
+            feature,
+            UsageEntry { project: Some("/other".to_string()), cost: 0.5, ..main.clone() },
+            UsageEntry { project: Some("/tmp".to_string()), git_branch: None, cost: 0.1, ..main.clone() },
+        ];
+        
+        let branches = build_branches(&entries);
+        assert_eq!(branches.len(), 4);
+        assert_eq!(branches[0].branch, "feat/x,y");
+        assert_eq!(branches[0].usage.entry_count, 2);
+        assert_eq!(branches[3].branch, NO_BRANCH);
+        
+        // The exact working directory seen on one branch labels them all
+        let decoded = UsageEntry { project: Some("/my/repo".to_string()), ..main.clone() };
+        let exact = UsageEntry {
+            cwd: Some("/my.repo".to_string()),
+            git_branch: Some("dev".to_string()),
+            ..decoded.clone()
+        };
+        let labelled = build_branches(&[decoded, exact]);
+        assert_eq!(labelled.len(), 2);
+        assert!(labelled.iter().all(|row| row.project == "/my.repo"));
+        
+        let csv = branches_csv(&branches);
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("project,branch,requests"));
+        assert!(lines.next().unwrap().starts_with("/repo,\"feat/x,y\",2,"));
+    }
+}
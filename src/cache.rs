@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::models::{LimitEvent, UsageEntry};
+
+/// Bump whenever the cached layout or the meaning of cached fields changes
+const CACHE_VERSION: u32 = 13;
+
+/// Number of leading bytes hashed to notice a file that was replaced in place
+const FINGERPRINT_BYTES: u64 = 4096;
+
+/// Parse state for a single JSONL file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFile {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+    /// Byte offset just past the last line that was parsed
+    pub offset: u64,
+    /// Hash of the first bytes of the parsed region
+    pub fingerprint: u64,
+    pub entries: Vec<UsageEntry>,
+    pub limit_events: Vec<LimitEvent>,
+}
+
+/// On-disk parse cache for one projects directory
+#[derive(Debug, Default)]
+pub struct ParseCache {
+    pub files: HashMap<PathBuf, CachedFile>,
+}
+
+impl ParseCache {
+    /// Load the cache for a projects directory, starting empty if it is
+    /// missing, unreadable or written by an incompatible version
+    pub fn load(projects_dir: &Path) -> Self {
+        cache_file_path(projects_dir)
+            .and_then(|path| Self::read(&path))
+            .unwrap_or_default()
+    }
+    
+    /// The file starts with the cache version so files from other versions
+    /// are rejected before their body is decoded
+    fn read(path: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        // Never let a corrupt length prefix allocate more than the file holds
+        let options = bincode::DefaultOptions::new().with_limit(file.metadata().ok()?.len());
+        let mut reader = BufReader::new(file);
+        
+        let version: u32 = options.deserialize_from(&mut reader).ok()?;
+        if version != CACHE_VERSION {
+            return None;
+        }
+        
+        let files = options.deserialize_from(&mut reader).ok()?;
+        Some(Self { files })
+    }
+    
+    /// Write the cache atomically so concurrent runs never see a partial file
+    pub fn save(&self, projects_dir: &Path) -> Result<()> {
+        let path = cache_file_path(projects_dir).context("Could not determine cache directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create cache directory")?;
+        }
+        
+        let tmp_path = path.with_extension(format!("tmp.{}", std::process::id()));
+        let file = File::create(&tmp_path).context("Failed to create cache file")?;
+        let mut writer = BufWriter::new(file);
+        let options = bincode::DefaultOptions::new();
+        options.serialize_into(&mut writer, &CACHE_VERSION).context("Failed to write cache")?;
+        options.serialize_into(&mut writer, &self.files).context("Failed to write cache")?;
+        writer.flush().context("Failed to write cache")?;
+        fs::rename(&tmp_path, &path).context("Failed to replace cache file")?;
+        
+        Ok(())
+    }
+}
+
+/// Directory holding parse caches (`$XDG_CACHE_HOME` or `~/.cache`)
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home::home_dir()?.join(".cache"),
+    };
+    Some(base.join("claude-block-checker"))
+}
+
+/// Each projects directory gets its own cache file named by a path hash
+fn cache_file_path(projects_dir: &Path) -> Option<PathBuf> {
+    let key = fnv1a(projects_dir.to_string_lossy().as_bytes());
+    Some(cache_dir()?.join(format!("{:016x}.bin", key)))
+}
+
+/// Hash the first bytes of a file, up to `limit`
+pub fn fingerprint(path: &Path, limit: u64) -> Result<u64> {
+    let file = File::open(path).context("Failed to open JSONL file")?;
+    let mut head = Vec::new();
+    file.take(limit.min(FINGERPRINT_BYTES))
+        .read_to_end(&mut head)
+        .context("Failed to read JSONL file")?;
+    Ok(fnv1a(&head))
+}
+
+/// FNV-1a, used because its output is stable across builds
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,191 @@
+use anyhow::{bail, Context, Result};
+use chrono::Duration;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::blocks::SESSION_DURATION_HOURS;
+use crate::limits::{LimitConfig, LimitMode};
+use crate::pricing::CostMode;
+
+/// Name of the global config file inside the config directory
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Contents of `~/.config/claude-block-checker/config.toml`. After CLI
+/// flags are merged in, this holds the effective settings.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directory holding one subdirectory per profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profiles_dir: Option<PathBuf>,
+    /// Length of a billing block
+    pub session_hours: u32,
+    /// Default for `--limit`
+    pub limit_mode: LimitMode,
+    /// Default for `--cost-mode`
+    pub cost_mode: CostMode,
+    /// Profiles left out unless named explicitly
+    pub hidden: Vec<String>,
+    /// Short names for profiles, e.g. `w = "work"`
+    pub aliases: BTreeMap<String, String>,
+    /// Default limits for profiles without their own `block-checker.toml`
+    pub limits: LimitConfig,
+    pub display: DisplayConfig,
+    /// Pricing file overlaid on the bundled prices (default: `pricing.toml`
+    /// or `pricing.json` next to this file)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pricing_file: Option<PathBuf>,
+    /// Profiles that live outside the profiles directory, by name
+    pub profiles: BTreeMap<String, PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            profiles_dir: None,
+            session_hours: SESSION_DURATION_HOURS as u32,
+            limit_mode: LimitMode::default(),
+            cost_mode: CostMode::default(),
+            hidden: Vec::new(),
+            aliases: BTreeMap::new(),
+            limits: LimitConfig::default(),
+            display: DisplayConfig::default(),
+            pricing_file: None,
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+/// Display preferences
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// Always show the detailed breakdown
+    pub detailed: bool,
+    /// Colour the output
+    pub color: bool,
+    /// Default timezone for reports and block history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// Time until limit below this many minutes is shown in yellow
+    pub warn_minutes: u64,
+    /// Time until limit below this many minutes is shown in red
+    pub critical_minutes: u64,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            detailed: false,
+            color: true,
+            timezone: None,
+            warn_minutes: 180,
+            critical_minutes: 60,
+        }
+    }
+}
+
+impl Config {
+    /// Load the global config, starting from the defaults when there is no
+    /// config file
+    pub fn load() -> Result<Self> {
+        let Some(path) = config_file_path() else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Config = toml::from_str(&contents)
+            .with_context(|| format!("Invalid {}", path.display()))?;
+        config.validate().with_context(|| format!("Invalid {}", path.display()))?;
+        Ok(config)
+    }
+    
+    fn validate(&self) -> Result<()> {
+        if self.session_hours == 0 {
+            bail!("session_hours must be at least 1");
+        }
+        if self.display.critical_minutes > self.display.warn_minutes {
+            bail!("display.critical_minutes must not exceed display.warn_minutes");
+        }
+        Ok(())
+    }
+    
+    pub fn session_duration(&self) -> Duration {
+        Duration::hours(self.session_hours as i64)
+    }
+    
+    /// The profile an alias stands for, or the name itself
+    pub fn resolve_alias<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+    
+    pub fn is_hidden(&self, name: &str) -> bool {
+        self.hidden.iter().any(|hidden| hidden == name)
+    }
+}
+
+/// Directory holding the config file (`$XDG_CONFIG_HOME` or `~/.config`)
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home::home_dir()?.join(".config"),
+    };
+    Some(base.join("claude-block-checker"))
+}
+
+pub fn config_file_path() -> Option<PathBuf> {
+    Some(config_dir()?.join(CONFIG_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Plan;
+    
+    #[test]
+    fn test_config_defaults_and_overrides() {
+        let config: Config = toml::from_str(
+            r#"
+            session_hours = 4
+            limit_mode = "max-all"
+            cost_mode = "calculate"
+            hidden = ["old"]
+            
+            [aliases]
+            w = "work"
+            
+            [limits]
+            plan = "max5"
+            
+            [display]
+            timezone = "Europe/London"
+            warn_minutes = 120
+            
+            [profiles]
+            work = "~/work/.claude"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.session_duration(), Duration::hours(4));
+        assert_eq!(config.limit_mode, LimitMode::MaxAll);
+        assert_eq!(config.cost_mode, CostMode::Calculate);
+        assert_eq!(config.limits.plan, Some(Plan::Max5));
+        assert_eq!(config.display.warn_minutes, 120);
+        assert_eq!(config.display.critical_minutes, 60);
+        assert!(config.display.color);
+        assert_eq!(config.resolve_alias("w"), "work");
+        assert_eq!(config.resolve_alias("home"), "home");
+        assert!(config.is_hidden("old"));
+        assert_eq!(config.profiles["work"], PathBuf::from("~/work/.claude"));
+        
+        assert!(toml::from_str::<Config>("session_hour = 4").is_err());
+        assert!(toml::from_str::<Config>("session_hours = 0").unwrap().validate().is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::models::{SessionBlock, CLAUDE_TOKEN_LIMIT};
+
+/// Per-profile config file, read from the profile's directory
+pub const PROFILE_CONFIG_FILE: &str = "block-checker.toml";
+
+/// Claude subscription plan of a profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Plan {
+    Pro,
+    Max5,
+    /// Default, matching the historical 300M token limit
+    #[default]
+    Max20,
+    /// No built-in token limit; set `token_limit` and/or `cost_limit`
+    Custom,
+}
+
+impl Plan {
+    /// Tokens allowed per 5-hour block
+    pub fn token_limit(self) -> Option<u64> {
+        match self {
+            Plan::Pro => Some(CLAUDE_TOKEN_LIMIT / 20),
+            Plan::Max5 => Some(CLAUDE_TOKEN_LIMIT / 4),
+            Plan::Max20 => Some(CLAUDE_TOKEN_LIMIT),
+            Plan::Custom => None,
+        }
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Plan::Pro => "Pro",
+            Plan::Max5 => "Max 5x",
+            Plan::Max20 => "Max 20x",
+            Plan::Custom => "Custom",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Where the limit used for projections comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LimitMode {
+    /// Limits of the profile's configured plan
+    #[default]
+    Plan,
+    /// Largest total reached by any past block of the profile
+    Max,
+    /// Largest total reached by any past block of any profile
+    MaxAll,
+}
+
+/// The past block that set an observed maximum
+#[derive(Debug, Clone, Serialize)]
+pub struct ObservedMax {
+    pub profile: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub total_tokens: u64,
+    pub total_cost: f64,
+    /// Whether the block ended with a usage limit message
+    pub hit_limit: bool,
+}
+
+/// Per-block usage limits of a profile
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageLimit {
+    pub plan: Plan,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_limit: Option<f64>,
+    /// Set when the token limit was learned from history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observed_max: Option<ObservedMax>,
+}
+
+impl Default for UsageLimit {
+    fn default() -> Self {
+        Self::for_plan(Plan::default())
+    }
+}
+
+impl UsageLimit {
+    pub fn for_plan(plan: Plan) -> Self {
+        Self {
+            plan,
+            token_limit: plan.token_limit(),
+            cost_limit: None,
+            observed_max: None,
+        }
+    }
+    
+    /// Replace the plan limits with the largest total seen in a past block.
+    /// Keeps the plan limits when there is no history to learn from.
+    pub fn with_observed_max(self, observed: Option<ObservedMax>) -> Self {
+        match observed {
+            Some(observed) => Self {
+                token_limit: Some(observed.total_tokens),
+                cost_limit: None,
+                observed_max: Some(observed),
+                ..self
+            },
+            None => self,
+        }
+    }
+    
+    /// Short description of the limit for display
+    pub fn label(&self) -> String {
+        if self.observed_max.is_some() {
+            "max observed".to_string()
+        } else {
+            self.plan.to_string()
+        }
+    }
+    
+    /// Share of the limit a block has used, as a percentage. With both a
+    /// token and a cost limit, whichever is closer to being reached wins.
+    pub fn percent_used(&self, block: &SessionBlock) -> Option<f64> {
+        let token_percent = self
+            .token_limit
+            .filter(|&limit| limit > 0)
+            .map(|limit| block.total_tokens as f64 / limit as f64 * 100.0);
+        let cost_percent = self
+            .cost_limit
+            .filter(|&limit| limit > 0.0)
+            .map(|limit| block.total_cost / limit * 100.0);
+        
+        match (token_percent, cost_percent) {
+            (Some(tokens), Some(cost)) => Some(tokens.max(cost)),
+            (tokens, cost) => tokens.or(cost),
+        }
+    }
+}
+
+/// Limit settings, from a profile's `block-checker.toml` or the `[limits]`
+/// defaults of the global config file
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Plan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_limit: Option<f64>,
+}
+
+impl LimitConfig {
+    /// These settings with the plan and token limit they resolve to filled
+    /// in, for showing the effective defaults
+    pub fn effective(&self) -> LimitConfig {
+        let limit = self.resolve(&LimitConfig::default());
+        LimitConfig {
+            plan: Some(limit.plan),
+            token_limit: limit.token_limit,
+            cost_limit: limit.cost_limit,
+        }
+    }
+    
+    /// Resolve these settings on top of `defaults`. A plan set here also
+    /// replaces a default token limit, so `plan = "pro"` means the Pro limit.
+    fn resolve(&self, defaults: &LimitConfig) -> UsageLimit {
+        let plan = self.plan.or(defaults.plan).unwrap_or_default();
+        let default_tokens = if self.plan.is_some() { None } else { defaults.token_limit };
+        
+        UsageLimit {
+            plan,
+            token_limit: self.token_limit.or(default_tokens).or(plan.token_limit()),
+            cost_limit: self.cost_limit.or(defaults.cost_limit),
+            observed_max: None,
+        }
+    }
+}
+
+/// Load the usage limits for a profile, falling back to `defaults` for
+/// anything the profile's config file does not set
+pub fn load_profile_limit(profile_path: &Path, defaults: &LimitConfig) -> Result<UsageLimit> {
+    let config_path = profile_path.join(PROFILE_CONFIG_FILE);
+    if !config_path.exists() {
+        return Ok(LimitConfig::default().resolve(defaults));
+    }
+    
+    let contents = fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read {}", config_path.display()))?;
+    let config: LimitConfig = toml::from_str(&contents)
+        .with_context(|| format!("Invalid {}", config_path.display()))?;
+    
+    Ok(config.resolve(defaults))
+}
+
+/// Find the completed block with the highest token total. Among equal
+/// totals, one that ran into a usage limit wins.
+pub fn max_observed_block(profile: &str, blocks: &[SessionBlock]) -> Option<ObservedMax> {
+    blocks
+        .iter()
+        .filter(|block| !block.is_active && !block.is_gap)
+        .max_by_key(|block| (block.total_tokens, !block.limit_events.is_empty()))
+        .map(|block| ObservedMax {
+            profile: profile.to_string(),
+            start_time: block.start_time,
+            end_time: block.end_time,
+            total_tokens: block.total_tokens,
+            total_cost: block.total_cost,
+            hit_limit: !block.limit_events.is_empty(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LimitEvent;
+    
+    #[test]
+    fn test_profile_config_overrides_plan() {
+        let config: LimitConfig = toml::from_str("plan = \"max5\"\ncost_limit = 35.0").unwrap();
+        let limit = config.resolve(&LimitConfig::default());
+        assert_eq!(limit.plan, Plan::Max5);
+        assert_eq!(limit.token_limit, Some(75_000_000));
+        assert_eq!(limit.cost_limit, Some(35.0));
+        
+        let effective = config.effective();
+        assert_eq!(effective.plan, Some(Plan::Max5));
+        assert_eq!(effective.token_limit, Some(75_000_000));
+        assert_eq!(LimitConfig::default().effective().plan, Some(Plan::default()));
+        
+        assert!(toml::from_str::<LimitConfig>("plan = \"max100\"").is_err());
+        
+        // Global defaults fill the gaps, but a profile's plan beats a
+        // default token limit
+        let defaults = LimitConfig { plan: None, token_limit: Some(200_000_000), cost_limit: Some(50.0) };
+        let limit = LimitConfig::default().resolve(&defaults);
+        assert_eq!(limit.token_limit, Some(200_000_000));
+        assert_eq!(limit.cost_limit, Some(50.0));
+        let limit = config.resolve(&defaults);
+        assert_eq!(limit.token_limit, Some(75_000_000));
+        assert_eq!(limit.cost_limit, Some(35.0));
+    }
+    
+    #[test]
+    fn test_max_observed_block_skips_active_and_gaps() {
+        let block = |tokens: u64, is_active: bool, is_gap: bool| SessionBlock {
+            start_time: Utc::now(),
+            end_time: Utc::now(),
+            actual_end_time: None,
+            is_active,
+            is_gap,
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_creation_tokens: 0,
+            cache_creation_5m_tokens: 0,
+            cache_creation_1h_tokens: 0,
+            cache_read_tokens: 0,
+            total_tokens: tokens,
+            web_search_requests: 0,
+            web_fetch_requests: 0,
+            total_cost: 0.0,
+            models: Vec::new(),
+            model_breakdown: Vec::new(),
+            threads: Default::default(),
+            cache: Default::default(),
+            entry_count: 0,
+            limit_events: Vec::new(),
+            burn_rate: None,
+        };
+        let blocks = vec![block(500, false, false), block(900, true, false), block(0, false, true)];
+        
+        let observed = max_observed_block("alpha", &blocks).unwrap();
+        assert_eq!(observed.total_tokens, 500);
+        
+        let limit = UsageLimit::default().with_observed_max(Some(observed));
+        assert_eq!(limit.token_limit, Some(500));
+        assert_eq!(limit.label(), "max observed");
+        assert!(max_observed_block("alpha", &blocks[1..]).is_none());
+        
+        // Hitting the limit only breaks ties, the largest total still wins
+        let limited = |tokens| {
+            let mut limited = block(tokens, false, false);
+            limited.limit_events.push(LimitEvent {
+                timestamp: Utc::now(),
+                reset_time: None,
+                message: "usage limit reached".to_string(),
+            });
+            limited
+        };
+        let observed = max_observed_block("alpha", &[block(500, false, false), limited(300)]).unwrap();
+        assert_eq!(observed.total_tokens, 500);
+        assert!(!observed.hit_limit);
+        let observed = max_observed_block("alpha", &[block(500, false, false), limited(500)]).unwrap();
+        assert!(observed.hit_limit);
+    }
+}
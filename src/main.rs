@@ -1,41 +1,102 @@
-use anyhow::{Context, Result};
-use chrono::{Duration, Utc};
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
 use clap::{Parser, Subcommand};
 use colored::*;
 use num_format::{Locale, ToFormattedString};
-use rayon::prelude::*;
 use serde::Serialize;
-use std::fs;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 mod models;
 mod parser;
 mod blocks;
+mod branches;
+mod cache;
+mod config;
+mod limits;
+mod pricing;
+mod profiles;
+mod projects;
+mod report;
+mod sessions;
+mod statusline;
+mod tools;
+mod usage;
+mod watch;
 
 use crate::models::*;
 use crate::parser::*;
 use crate::blocks::*;
+use crate::branches::*;
+use crate::limits::*;
+use crate::pricing::*;
+use crate::profiles::*;
+use crate::projects::*;
+use crate::report::*;
+use crate::sessions::*;
+use crate::tools::*;
+use crate::usage::*;
 
 #[derive(Parser)]
 #[command(name = "claude-block-checker")]
 #[command(about = "Check Claude Code usage for current billing blocks", long_about = None)]
 struct Cli {
     #[command(subcommand)]
-    command: Option<Commands>,
+    command: Option<CliCommand>,
     
     /// Profile name to check (if not specified, checks all profiles)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     profile: Option<String>,
     
     /// Show detailed breakdown
-    #[arg(short = 'd', long)]
+    #[arg(short = 'd', long, global = true)]
     detailed: bool,
     
     /// Output in JSON format
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     json: bool,
+    
+    /// Re-parse every log file instead of using the parse cache
+    #[arg(long, global = true)]
+    no_cache: bool,
+    
+    /// Limit used for projections: the profile's plan, or the largest
+    /// block seen in the profile's (max) or any profile's (max-all) history
+    /// [default: plan]
+    #[arg(long, value_enum, global = true)]
+    limit: Option<LimitMode>,
+    
+    /// Where costs come from: the logged costUSD with calculation as a
+    /// fallback (auto), always the pricing table (calculate), or only the
+    /// logged cost (display) [default: auto]
+    #[arg(long, value_enum, global = true)]
+    cost_mode: Option<CostMode>,
+    
+    /// Fail instead of guessing prices for models no pricing rule names
+    #[arg(long, global = true)]
+    strict_pricing: bool,
+    
+    /// Directory holding one subdirectory per profile (default: ~/claude-profiles)
+    #[arg(long, env = PROFILES_DIR_ENV, global = true)]
+    profiles_dir: Option<PathBuf>,
+}
+
+/// Every subcommand; `config` is kept apart because it runs without any
+/// profiles
+#[derive(Subcommand)]
+enum CliCommand {
+    #[command(flatten)]
+    Profiles(Commands),
+    
+    /// Inspect the configuration file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
 }
 
+/// Subcommands that read the profiles' usage
 #[derive(Subcommand)]
 enum Commands {
     /// List all available profiles
@@ -49,116 +110,387 @@ enum Commands {
     
     /// Show current block for all profiles (default)
     All,
+    
+    /// Usage totals per day, week or month
+    Report {
+        /// Period to group usage by
+        #[arg(value_enum)]
+        period: ReportPeriod,
+        
+        /// First date to include (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        
+        /// Last date to include (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        
+        /// IANA timezone for calendar periods, e.g. Europe/London (default: local)
+        #[arg(long)]
+        timezone: Option<Tz>,
+        
+        /// Show each profile's usage within every period
+        #[arg(short, long)]
+        breakdown: bool,
+    },
+    
+    /// List past session blocks
+    Blocks {
+        /// Only show the N most recent blocks per profile
+        #[arg(short, long)]
+        recent: Option<usize>,
+        
+        /// First date to include (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        
+        /// Last date to include (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        
+        /// IANA timezone for dates and times (default: local)
+        #[arg(long)]
+        timezone: Option<Tz>,
+    },
+    
+    /// Rank projects by usage in the active blocks, or over a date range
+    Projects {
+        /// First date to include (YYYY-MM-DD). Without --since or --until
+        /// only the active blocks count.
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        
+        /// Last date to include (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        
+        /// IANA timezone for dates (default: local)
+        #[arg(long)]
+        timezone: Option<Tz>,
+        
+        /// Rank by tokens or cost
+        #[arg(long, value_enum, default_value_t = ProjectSort::Tokens)]
+        sort: ProjectSort,
+    },
+    
+    /// Tool calls and the output tokens spent on each tool
+    Tools {
+        /// First date to include (YYYY-MM-DD). Without --since or --until
+        /// only the active blocks count.
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        
+        /// Last date to include (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        
+        /// IANA timezone for dates (default: local)
+        #[arg(long)]
+        timezone: Option<Tz>,
+        
+        /// Break the report down by session, project or block
+        #[arg(long, value_enum)]
+        by: Option<ToolGrouping>,
+    },
+    
+    /// Tokens and cost per project and git branch
+    Branches {
+        /// First date to include (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        
+        /// Last date to include (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        
+        /// IANA timezone for dates (default: local)
+        #[arg(long)]
+        timezone: Option<Tz>,
+        
+        /// Output as CSV (not with --json)
+        #[arg(long)]
+        csv: bool,
+    },
+    
+    /// List sessions, or show one session's requests
+    Sessions {
+        /// Session ID, or the start of one, to show request by request
+        session: Option<String>,
+        
+        /// Only sessions whose project path contains this text
+        #[arg(long)]
+        project: Option<String>,
+        
+        /// Only show the N most recent sessions
+        #[arg(short, long)]
+        recent: Option<usize>,
+        
+        /// First date to include (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        
+        /// Last date to include (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        
+        /// IANA timezone for dates and times (default: local)
+        #[arg(long)]
+        timezone: Option<Tz>,
+    },
+    
+    /// Live dashboard of the current blocks, refreshed in place
+    Watch {
+        /// Seconds between reloads
+        #[arg(short, long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
+    
+    /// One-line summary for Claude Code's statusLine command
+    Statusline,
+    
+    /// Inspect model pricing
+    Pricing {
+        #[command(subcommand)]
+        command: PricingCommand,
+    },
 }
 
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective settings, with command line flags applied
+    Show,
+}
+
+#[derive(Subcommand)]
+enum PricingCommand {
+    /// Show which pricing rule matched each model in the logs
+    List,
+    
+    /// Compare logged costs with the pricing table, per model
+    Compare {
+        /// Largest difference, in percent, that still counts as a match
+        #[arg(short, long, default_value_t = 1.0)]
+        tolerance: f64,
+    },
+}
+
+/// A past or active block with how close it came to the limit
 #[derive(Debug, Serialize)]
-struct ProfileUsage {
-    name: String,
-    active_block: Option<SessionBlock>,
-    total_tokens: u64,
-    total_cost: f64,
-    models_used: Vec<String>,
+struct BlockHistoryRow {
+    #[serde(flatten)]
+    block: SessionBlock,
     #[serde(skip_serializing_if = "Option::is_none")]
-    minutes_until_limit: Option<u64>,
+    limit_used_percent: Option<f64>,
+}
+
+/// Block history of one profile
+#[derive(Debug, Serialize)]
+struct BlockHistory {
+    name: String,
+    limit: UsageLimit,
+    blocks: Vec<BlockHistoryRow>,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
     
-    let profiles_dir = home::home_dir()
-        .context("Could not find home directory")?
-        .join("claude-profiles");
+    // Command line flags take precedence over the config file
+    let mut config = config::Config::load()?;
+    if let Some(ref dir) = cli.profiles_dir {
+        config.profiles_dir = Some(dir.clone());
+    }
+    if let Some(mode) = cli.limit {
+        config.limit_mode = mode;
+    }
+    if let Some(mode) = cli.cost_mode {
+        config.cost_mode = mode;
+    }
+    config.display.detailed |= cli.detailed;
+    
+    if !config.display.color {
+        colored::control::set_override(false);
+    }
+    
+    let command = match cli.command {
+        Some(CliCommand::Config { command: ConfigCommand::Show }) => return show_config(config, &cli),
+        Some(CliCommand::Profiles(command)) => Some(command),
+        None => None,
+    };
+    
+    let profile_name = match command {
+        Some(Commands::Check { profile: Some(ref profile) }) => Some(profile),
+        _ => cli.profile.as_ref(),
+    }
+    .map(|name| config.resolve_alias(name).to_string());
+    
+    let mut profiles = discover_profiles(&config)?;
+    // Hidden profiles only show up when asked for by name, or when the
+    // status line runs inside one of them
+    if !matches!(command, Some(Commands::Statusline)) {
+        profiles.retain(|(name, _)| !config.is_hidden(name) || profile_name.as_ref() == Some(name));
+    }
     
-    if !profiles_dir.exists() {
-        eprintln!("{}", "Error: ~/claude-profiles directory not found".red());
+    if profiles.is_empty() {
+        eprintln!("{}", "Error: no Claude profiles found".red());
+        eprintln!("Create ~/claude-profiles/<name>, pass --profiles-dir or list profiles in the config file");
         std::process::exit(1);
     }
     
-    match cli.command {
-        Some(Commands::List) => list_profiles(&profiles_dir),
-        Some(Commands::Check { profile }) => {
-            if let Some(profile_name) = profile.or(cli.profile) {
-                check_single_profile(&profiles_dir, &profile_name, cli.detailed, cli.json)
+    let pricing_file = config.pricing_file.as_deref().map(|path| expand_home(path, home::home_dir().as_deref()));
+    let pricing = PricingTable::load(pricing_file.as_deref())?;
+    let options = CheckOptions {
+        detailed: config.display.detailed,
+        json: cli.json,
+        use_cache: !cli.no_cache,
+        limit_mode: config.limit_mode,
+        cost_mode: config.cost_mode,
+        strict_pricing: cli.strict_pricing,
+        limit_defaults: config.limits,
+        session_duration: config.session_duration(),
+        warn_minutes: config.display.warn_minutes,
+        critical_minutes: config.display.critical_minutes,
+        pricing: &pricing,
+    };
+    let profile_name = profile_name.as_deref();
+    
+    match command {
+        Some(Commands::List) => list_profiles(&profiles),
+        Some(Commands::Check { .. }) => {
+            if let Some(profile_name) = profile_name {
+                check_single_profile(&profiles, profile_name, options)
             } else {
-                check_all_profiles(&profiles_dir, cli.detailed, cli.json)
+                check_all_profiles(&profiles, options)
             }
         }
         Some(Commands::All) | None => {
-            check_all_profiles(&profiles_dir, cli.detailed, cli.json)
+            check_all_profiles(&profiles, options)
+        }
+        Some(Commands::Report { period, since, until, timezone, breakdown }) => {
+            let zone = ReportZone::new(timezone.or(config.display.timezone));
+            let filter = DateFilter { since, until, zone };
+            show_report(&profiles, profile_name, period, &filter, breakdown, options)
+        }
+        Some(Commands::Blocks { recent, since, until, timezone }) => {
+            let zone = ReportZone::new(timezone.or(config.display.timezone));
+            let filter = DateFilter { since, until, zone };
+            list_blocks(&profiles, profile_name, recent, &filter, options)
+        }
+        Some(Commands::Projects { since, until, timezone, sort }) => {
+            let zone = ReportZone::new(timezone.or(config.display.timezone));
+            let window = UsageWindow::new(DateFilter { since, until, zone });
+            show_projects(&profiles, profile_name, &window, sort, options)
+        }
+        Some(Commands::Tools { since, until, timezone, by }) => {
+            let zone = ReportZone::new(timezone.or(config.display.timezone));
+            let window = UsageWindow::new(DateFilter { since, until, zone });
+            show_tools(&profiles, profile_name, &window, by, &zone, options)
+        }
+        Some(Commands::Branches { since, until, timezone, csv }) => {
+            // --json is global, so clap cannot tell a conflict given before
+            // the subcommand
+            if csv && cli.json {
+                bail!("--csv cannot be used with --json");
+            }
+            let zone = ReportZone::new(timezone.or(config.display.timezone));
+            show_branches(&profiles, profile_name, DateFilter { since, until, zone }, csv, options)
+        }
+        Some(Commands::Sessions { ref session, ref project, recent, since, until, timezone }) => {
+            let zone = ReportZone::new(timezone.or(config.display.timezone));
+            let filter = DateFilter { since, until, zone };
+            let query = SessionQuery {
+                session: session.as_deref(),
+                project: project.as_deref(),
+                recent,
+                filter: &filter,
+            };
+            show_sessions(&profiles, profile_name, query, options)
+        }
+        Some(Commands::Watch { interval }) => {
+            watch::run_watch(&profiles, profile_name, std::time::Duration::from_secs(interval), options)
+        }
+        Some(Commands::Statusline) => {
+            statusline::run_statusline(&profiles, profile_name, options)
+        }
+        Some(Commands::Pricing { command: PricingCommand::List }) => {
+            list_pricing(&profiles, profile_name, options)
+        }
+        Some(Commands::Pricing { command: PricingCommand::Compare { tolerance } }) => {
+            compare_pricing(&profiles, profile_name, tolerance, options)
         }
     }
 }
 
-fn list_profiles(profiles_dir: &Path) -> Result<()> {
-    println!("{}", "Available Claude Profiles:".bold().green());
+/// Settings shown by `config show`: the merged config plus the flags that
+/// only exist on the command line
+#[derive(Serialize)]
+struct EffectiveConfig<'a> {
+    strict_pricing: bool,
+    no_cache: bool,
+    #[serde(flatten)]
+    config: &'a config::Config,
+}
+
+/// Print the effective settings as TOML, or JSON with `--json`
+fn show_config(mut config: config::Config, cli: &Cli) -> Result<()> {
+    config.limits = config.limits.effective();
+    let effective = EffectiveConfig {
+        strict_pricing: cli.strict_pricing,
+        no_cache: cli.no_cache,
+        config: &config,
+    };
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(&effective)?);
+        return Ok(());
+    }
     
-    let mut profiles = Vec::new();
-    for entry in fs::read_dir(profiles_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_dir() {
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                if !name.starts_with('.') {
-                    profiles.push(name.to_string());
-                }
-            }
-        }
+    match config::config_file_path() {
+        Some(path) if path.exists() => println!("# Config file: {}", path.display()),
+        Some(path) => println!("# Config file: {} (not found, using defaults)", path.display()),
+        None => println!("# Config file: none (no home directory)"),
     }
+    print!("{}", toml::to_string_pretty(&effective).context("Failed to format config")?);
+    
+    Ok(())
+}
+
+fn list_profiles(profiles: &[Profile]) -> Result<()> {
+    println!("{}", "Available Claude Profiles:".bold().green());
     
-    profiles.sort();
-    for profile in profiles {
-        println!("  • {}", profile.cyan());
+    for (profile, path) in profiles {
+        println!("  • {} {}", profile.cyan(), path.display().to_string().dimmed());
     }
     
     Ok(())
 }
 
 fn check_single_profile(
-    profiles_dir: &Path,
+    profiles: &[Profile],
     profile_name: &str,
-    detailed: bool,
-    json: bool,
+    options: CheckOptions,
 ) -> Result<()> {
-    let profile_path = profiles_dir.join(profile_name);
-    
-    if !profile_path.exists() {
-        eprintln!("{}", format!("Profile '{}' not found", profile_name).red());
-        std::process::exit(1);
-    }
-    
-    let usage = check_profile(&profile_path, profile_name, detailed)?;
+    let (loaded, limit) = load_selected_with_limits(profiles, Some(profile_name), options)?
+        .into_iter()
+        .next()
+        .map(|(_, result)| result)
+        .context("Profile disappeared while checking")??;
+    let usage = summarize_profile(profile_name, loaded, limit);
     
-    if json {
+    if options.json {
         println!("{}", serde_json::to_string_pretty(&usage)?);
     } else {
-        print_profile_usage(&usage, detailed);
+        print_profile_usage(&usage, options);
     }
     
     Ok(())
 }
 
-fn check_all_profiles(profiles_dir: &Path, detailed: bool, json: bool) -> Result<()> {
+fn check_all_profiles(profiles: &[Profile], options: CheckOptions) -> Result<()> {
+    let json = options.json;
     let mut all_usage = Vec::new();
     let mut total_tokens = 0u64;
     let mut total_cost = 0.0f64;
     let mut active_count = 0;
     
-    // Get all profiles
-    let mut profiles = Vec::new();
-    for entry in fs::read_dir(profiles_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_dir() {
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                if !name.starts_with('.') {
-                    profiles.push((name.to_string(), path));
-                }
-            }
-        }
-    }
-    
-    profiles.sort_by(|a, b| a.0.cmp(&b.0));
-    
     if !json {
         println!("{}", "Claude Code Usage - Current Block Report".bold().green());
         println!("Time: {}", Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
@@ -166,17 +498,13 @@ fn check_all_profiles(profiles_dir: &Path, detailed: bool, json: bool) -> Result
     }
     
     // Check each profile in parallel
-    let results: Vec<(String, Result<ProfileUsage>)> = profiles
-        .par_iter()
-        .map(|(name, path)| {
-            (name.clone(), check_profile(path, name, detailed))
-        })
-        .collect();
+    let results = load_profiles_with_limits(profiles, options)?;
     
     // Process results in order
     for (name, result) in results {
         match result {
-            Ok(usage) => {
+            Ok((loaded, limit)) => {
+                let usage = summarize_profile(&name, loaded, limit);
                 if usage.active_block.is_some() {
                     active_count += 1;
                     total_tokens += usage.total_tokens;
@@ -184,7 +512,7 @@ fn check_all_profiles(profiles_dir: &Path, detailed: bool, json: bool) -> Result
                 }
                 
                 if !json {
-                    print_profile_usage(&usage, detailed);
+                    print_profile_usage(&usage, options);
                 }
                 
                 all_usage.push(usage);
@@ -198,8 +526,9 @@ fn check_all_profiles(profiles_dir: &Path, detailed: bool, json: bool) -> Result
         }
     }
     
-    // Find profile with most time remaining
+    // Find profile with most time remaining, skipping rate limited ones
     let best_profile = all_usage.iter()
+        .filter(|p| p.rate_limited_until.is_none())
         .filter(|p| p.minutes_until_limit.is_some())
         .max_by_key(|p| p.minutes_until_limit.unwrap_or(0));
     
@@ -211,6 +540,11 @@ fn check_all_profiles(profiles_dir: &Path, detailed: bool, json: bool) -> Result
             "total_cost": total_cost,
         });
         
+        let unknown = merge_unknown_models(all_usage.iter().map(|usage| &usage.unknown_models[..]));
+        if !unknown.is_empty() {
+            summary["unknown_models"] = serde_json::json!(unknown);
+        }
+        
         if let Some(best) = best_profile {
             summary["recommended_profile"] = serde_json::json!({
                 "name": best.name,
@@ -235,13 +569,7 @@ fn check_all_profiles(profiles_dir: &Path, detailed: bool, json: bool) -> Result
             // Show recommended profile
             if let Some(best) = best_profile {
                 if let Some(minutes) = best.minutes_until_limit {
-                    let hours = minutes / 60;
-                    let mins = minutes % 60;
-                    let time_str = if hours > 0 {
-                        format!("{}h {}m", hours, mins)
-                    } else {
-                        format!("{}m", mins)
-                    };
+                    let time_str = format_minutes(minutes);
                     
                     println!("\n{}", "Recommended Profile:".bold().cyan());
                     println!("  {} → {} until limit", best.name.cyan().bold(), time_str.green());
@@ -253,131 +581,431 @@ fn check_all_profiles(profiles_dir: &Path, detailed: bool, json: bool) -> Result
     Ok(())
 }
 
-fn check_profile(profile_path: &Path, profile_name: &str, detailed: bool) -> Result<ProfileUsage> {
-    let projects_dir = profile_path.join("projects");
-    
-    if !projects_dir.exists() {
-        return Ok(ProfileUsage {
-            name: profile_name.to_string(),
-            active_block: None,
-            total_tokens: 0,
-            total_cost: 0.0,
-            models_used: Vec::new(),
-            minutes_until_limit: None,
-        });
+fn show_report(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    period: ReportPeriod,
+    filter: &DateFilter,
+    breakdown: bool,
+    options: CheckOptions,
+) -> Result<()> {
+    let profiles = select_profiles(profiles, profile_name)?;
+    let entries: Vec<(String, Vec<UsageEntry>)> = load_profiles_entries(&profiles, options)?
+        .into_iter()
+        .map(|(name, loaded)| (name, loaded.entries))
+        .collect();
+    
+    let mut report = build_report(&entries, period, filter, breakdown);
+    let in_range = entries
+        .iter()
+        .flat_map(|(_, entries)| entries)
+        .filter(|entry| filter.contains(entry.timestamp));
+    report.unknown_models = unknown_models(options.pricing, in_range, options.cost_mode);
+    
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
     }
     
-    // Load all usage entries
-    let entries = load_usage_entries(&projects_dir)?;
+    Ok(())
+}
+
+/// The entries a usage breakdown looks at
+enum UsageWindow {
+    /// Each profile's active block
+    ActiveBlocks,
+    Dates(DateFilter),
+}
+
+impl UsageWindow {
+    /// A date range when either end is given, else the active blocks
+    fn new(filter: DateFilter) -> Self {
+        if filter.since.is_none() && filter.until.is_none() {
+            UsageWindow::ActiveBlocks
+        } else {
+            UsageWindow::Dates(filter)
+        }
+    }
     
-    if entries.is_empty() {
-        return Ok(ProfileUsage {
-            name: profile_name.to_string(),
-            active_block: None,
-            total_tokens: 0,
-            total_cost: 0.0,
-            models_used: Vec::new(),
-            minutes_until_limit: None,
-        });
+    fn describe(&self) -> String {
+        match self {
+            UsageWindow::ActiveBlocks => "active blocks".to_string(),
+            UsageWindow::Dates(filter) => {
+                let range = match (filter.since, filter.until) {
+                    (Some(since), Some(until)) => format!("{} to {}", since, until),
+                    (Some(since), None) => format!("since {}", since),
+                    (None, Some(until)) => format!("until {}", until),
+                    (None, None) => "all time".to_string(),
+                };
+                format!("{} ({})", range, filter.zone.name())
+            }
+        }
     }
     
-    // Identify session blocks
-    let blocks = identify_session_blocks(entries);
+    /// Each profile's entries that fall in the window
+    fn select<'a>(&self, loaded: &'a [(String, LoadedUsage)], options: CheckOptions) -> Vec<(&'a str, &'a UsageEntry)> {
+        let mut selected = Vec::new();
+        for (name, loaded) in loaded {
+            let entries = &loaded.entries;
+            match self {
+                UsageWindow::ActiveBlocks => {
+                    let active = identify_session_blocks(entries.clone(), options.session_duration)
+                        .into_iter()
+                        .find(|block| block.is_active);
+                    if let Some(block) = active {
+                        selected.extend(
+                            entries
+                                .iter()
+                                .filter(|entry| block.start_time <= entry.timestamp && entry.timestamp < block.end_time)
+                                .map(|entry| (name.as_str(), entry)),
+                        );
+                    }
+                }
+                UsageWindow::Dates(filter) => {
+                    selected.extend(
+                        entries
+                            .iter()
+                            .filter(|entry| filter.contains(entry.timestamp))
+                            .map(|entry| (name.as_str(), entry)),
+                    );
+                }
+            }
+        }
+        selected
+    }
+}
+
+fn show_projects(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    window: &UsageWindow,
+    sort: ProjectSort,
+    options: CheckOptions,
+) -> Result<()> {
+    let profiles = select_profiles(profiles, profile_name)?;
+    let loaded = load_profiles_entries(&profiles, options)?;
+    let report = build_projects_report(window.select(&loaded, options), sort, window.describe());
     
-    // Find active block
-    let mut active_block = blocks.into_iter()
-        .find(|block| block.is_active);
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_projects_report(&report);
+    }
     
-    // Always calculate burn rate to determine time until limit
-    if let Some(ref mut block) = active_block {
-            let now = Utc::now();
-            let elapsed = (now - block.start_time).num_minutes() as f64;
-            if elapsed > 1.0 {
-                let tokens_per_minute = (block.total_tokens as f64 / elapsed) as u64;
-                let cost_per_hour = (block.total_cost / elapsed) * 60.0;
-                
-                let remaining_minutes = (5.0 * 60.0) - elapsed;
-                let projected_tokens = if remaining_minutes > 0.0 {
-                    (block.total_tokens as f64 + (tokens_per_minute as f64 * remaining_minutes)) as u64
-                } else {
-                    block.total_tokens
-                };
-                let projected_cost = if remaining_minutes > 0.0 {
-                    block.total_cost + (cost_per_hour * remaining_minutes / 60.0)
-                } else {
-                    block.total_cost
-                };
-                
-                // Calculate time until limit
-                let time_until_limit = if tokens_per_minute > 0 {
-                    let tokens_remaining = models::CLAUDE_TOKEN_LIMIT.saturating_sub(block.total_tokens);
-                    let minutes_until_limit = tokens_remaining / tokens_per_minute;
-                    
-                    // Format human readable time
-                    let hours = minutes_until_limit / 60;
-                    let mins = minutes_until_limit % 60;
-                    let human_readable = if hours > 0 {
-                        format!("{}h {}m", hours, mins)
-                    } else {
-                        format!("{}m", mins)
-                    };
-                    
-                    Some(models::TimeUntilLimit {
-                        minutes: minutes_until_limit,
-                        human_readable,
-                    })
-                } else {
-                    None
-                };
-                
-                block.burn_rate = Some(models::BurnRate {
-                    elapsed_minutes: elapsed as u64,
-                    tokens_per_minute,
-                    cost_per_hour,
-                    projected_tokens,
-                    projected_cost,
-                    time_until_limit,
-                });
+    Ok(())
+}
+
+fn show_tools(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    window: &UsageWindow,
+    by: Option<ToolGrouping>,
+    zone: &ReportZone,
+    options: CheckOptions,
+) -> Result<()> {
+    let profiles = select_profiles(profiles, profile_name)?;
+    let loaded = load_profiles_entries(&profiles, options)?;
+    let blocks: HashMap<&str, Vec<SessionBlock>> = if by == Some(ToolGrouping::Block) {
+        loaded
+            .iter()
+            .map(|(name, loaded)| {
+                (name.as_str(), identify_session_blocks(loaded.entries.clone(), options.session_duration))
+            })
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    let report = build_tools_report(window.select(&loaded, options), by, &blocks, zone, window.describe());
+    
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_tools_report(&report);
+    }
+    
+    Ok(())
+}
+
+fn show_branches(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    filter: DateFilter,
+    csv: bool,
+    options: CheckOptions,
+) -> Result<()> {
+    let profiles = select_profiles(profiles, profile_name)?;
+    let zone = filter.zone;
+    // Unlike the projects report, no dates means all time
+    let window = UsageWindow::Dates(filter);
+    let loaded = load_profiles_entries(&profiles, options)?;
+    let branches = build_branches(window.select(&loaded, options).into_iter().map(|(_, entry)| entry));
+    
+    if csv {
+        print!("{}", branches_csv(&branches));
+    } else if options.json {
+        println!("{}", serde_json::to_string_pretty(&branches)?);
+    } else {
+        print_branches(&branches, &window.describe(), &zone);
+    }
+    
+    Ok(())
+}
+
+/// Which sessions to list, or the one to show in detail
+struct SessionQuery<'a> {
+    session: Option<&'a str>,
+    project: Option<&'a str>,
+    recent: Option<usize>,
+    filter: &'a DateFilter,
+}
+
+fn show_sessions(profiles: &[Profile], profile_name: Option<&str>, query: SessionQuery, options: CheckOptions) -> Result<()> {
+    let profiles = select_profiles(profiles, profile_name)?;
+    let loaded = load_profiles_entries(&profiles, options)?;
+    
+    let mut sessions = Vec::new();
+    for (name, loaded) in &loaded {
+        let blocks = identify_session_blocks(loaded.entries.clone(), options.session_duration);
+        sessions.extend(build_sessions(name, &loaded.entries, &blocks));
+    }
+    sessions.retain(|(session, _)| {
+        query.filter.contains(session.start_time)
+            && query.project.is_none_or(|project| session.project.contains(project))
+    });
+    sessions.sort_by_key(|(session, _)| session.start_time);
+    
+    if let Some(id) = query.session {
+        let mut matches: Vec<_> = sessions
+            .into_iter()
+            .filter(|(session, _)| session.session_id.starts_with(id))
+            .collect();
+        let (session, entries) = match matches.len() {
+            0 => bail!("Session '{}' not found", id),
+            1 => matches.remove(0),
+            n => bail!("Session '{}' is ambiguous ({} sessions match)", id, n),
+        };
+        let detail = session_detail(session, &entries);
+        if options.json {
+            println!("{}", serde_json::to_string_pretty(&detail)?);
+        } else {
+            print_session_detail(&detail, &query.filter.zone);
+        }
+        return Ok(());
+    }
+    
+    if let Some(recent) = query.recent {
+        sessions.drain(..sessions.len().saturating_sub(recent));
+    }
+    let sessions: Vec<SessionSummary> = sessions.into_iter().map(|(session, _)| session).collect();
+    
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&sessions)?);
+    } else {
+        print_sessions(&sessions, &query.filter.zone);
+    }
+    
+    Ok(())
+}
+
+/// Show the pricing rule behind each model seen in the selected profiles
+fn list_pricing(profiles: &[Profile], profile_name: Option<&str>, options: CheckOptions) -> Result<()> {
+    let profiles = select_profiles(profiles, profile_name)?;
+    let entries: Vec<UsageEntry> = load_profiles_entries(&profiles, options)?
+        .into_iter()
+        .flat_map(|(_, loaded)| loaded.entries)
+        .collect();
+    let rows = match_models(options.pricing, &entries, options.cost_mode);
+    
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else {
+        print_pricing_matches(&rows);
+    }
+    
+    Ok(())
+}
+
+fn compare_pricing(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    tolerance: f64,
+    options: CheckOptions,
+) -> Result<()> {
+    let profiles = select_profiles(profiles, profile_name)?;
+    let entries: Vec<UsageEntry> = load_profiles_entries(&profiles, options)?
+        .into_iter()
+        .flat_map(|(_, loaded)| loaded.entries)
+        .collect();
+    let rows = compare_costs(options.pricing, &entries, tolerance);
+    
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else {
+        print_cost_comparison(&rows, tolerance);
+    }
+    
+    Ok(())
+}
+
+fn list_blocks(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    recent: Option<usize>,
+    filter: &DateFilter,
+    options: CheckOptions,
+) -> Result<()> {
+    let now = Utc::now();
+    let mut histories = Vec::new();
+    
+    for (name, result) in load_selected_with_limits(profiles, profile_name, options)? {
+        let (loaded, limit) = match result {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("{}", format!("Profile {}: {}", name, e).red());
+                continue;
             }
+        };
+        
+        let blocks = select_blocks(loaded.blocks, recent, filter)
+            .into_iter()
+            .map(|mut block| {
+                if block.is_active {
+                    block.burn_rate = calculate_burn_rate(&block, &limit, now);
+                }
+                BlockHistoryRow {
+                    limit_used_percent: (!block.is_gap).then(|| limit.percent_used(&block)).flatten(),
+                    block,
+                }
+            })
+            .collect();
+        
+        histories.push(BlockHistory { name, limit, blocks });
     }
     
-    if let Some(ref block) = active_block {
-        let minutes_until_limit = block.burn_rate.as_ref()
-            .and_then(|br| br.time_until_limit.as_ref())
-            .map(|tul| tul.minutes);
-            
-        Ok(ProfileUsage {
-            name: profile_name.to_string(),
-            total_tokens: block.total_tokens,
-            total_cost: block.total_cost,
-            models_used: block.models.clone(),
-            minutes_until_limit,
-            active_block,
-        })
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&histories)?);
     } else {
-        Ok(ProfileUsage {
-            name: profile_name.to_string(),
-            active_block: None,
-            total_tokens: 0,
-            total_cost: 0.0,
-            models_used: Vec::new(),
-            minutes_until_limit: None,
-        })
+        for history in &histories {
+            print_block_history(history, &filter.zone);
+        }
     }
+    
+    Ok(())
 }
 
-fn print_profile_usage(usage: &ProfileUsage, detailed: bool) {
+fn print_block_history(history: &BlockHistory, zone: &ReportZone) {
+    println!("{} {} ({} limit)", "━━━ Profile:".bold().blue(), history.name.bold().blue(), history.limit.label());
+    
+    if history.blocks.is_empty() {
+        println!("  {}\n", "No blocks in the selected range".yellow());
+        return;
+    }
+    
+    println!("{}", format!(
+        "  {:<16} {:<16} {:>7} {:>15} {:>10} {:>9} {:>6} {:>8}  Models",
+        "Start", "End", "Entries", "Tokens", "Cost", "Subagent", "Hit", "Limit"
+    ).bold());
+    
+    for row in &history.blocks {
+        let block = &row.block;
+        let start = zone.format(block.start_time, "%Y-%m-%d %H:%M");
+        let end = zone.format(block.end_time, "%Y-%m-%d %H:%M");
+        
+        if block.is_gap {
+            println!("{}", format!("  {:<16} {:<16} {:>7}", start, end, "(idle)").dimmed());
+            continue;
+        }
+        
+        let percent = row.limit_used_percent
+            .map_or_else(|| "-".to_string(), |percent| format!("{:.1}%", percent));
+        let percent = match row.limit_used_percent {
+            Some(p) if p >= 100.0 => percent.red(),
+            Some(p) if p >= 80.0 => percent.yellow(),
+            _ => percent.normal(),
+        };
+        let mut models = block.models.clone();
+        models.sort();
+        
+        println!(
+            "  {:<16} {:<16} {:>7} {:>15} {:>10} {:>9} {:>6} {:>8}  {}{}{}",
+            start,
+            end,
+            block.entry_count,
+            block.total_tokens.to_formatted_string(&Locale::en),
+            format!("${:.2}", block.total_cost),
+            block.threads.subagent_label(),
+            block.cache.hit_label(),
+            percent,
+            models.join(", "),
+            if block.is_active { " ●".green().to_string() } else { String::new() },
+            if block.limit_events.is_empty() { String::new() } else { " ✖ limit reached".red().to_string() },
+        );
+    }
+    
+    println!();
+}
+
+/// Tokens, cost and requests of each model in a block, with its share of
+/// the block's tokens
+fn print_model_breakdown(block: &SessionBlock) {
+    let width = block.model_breakdown.iter().map(|usage| usage.model.len()).max().unwrap_or(0);
+    println!("\n  {}:", "Per Model".bold());
+    for usage in &block.model_breakdown {
+        let share = if block.total_tokens > 0 {
+            usage.total_tokens as f64 / block.total_tokens as f64 * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "    {:<width$}  {:>14} tok ({:>5.1}%)  {:>10}  {:>6} req",
+            usage.model,
+            usage.total_tokens.to_formatted_string(&Locale::en),
+            share,
+            format!("${:.4}", usage.cost),
+            usage.request_count.to_formatted_string(&Locale::en),
+            width = width
+        );
+    }
+}
+
+/// Tokens, cost and requests of the main conversation and of its subagents
+fn print_thread_split(block: &SessionBlock) {
+    let threads = &block.threads;
+    let share = threads.subagent_share();
+    println!("\n  {}:", "Threads".bold());
+    for (label, usage, share) in [("Main", &threads.main, 100.0 - share), ("Subagents", &threads.subagent, share)] {
+        println!(
+            "    {:<9}  {:>14} tok ({:>5.1}%)  {:>10}  {:>6} req",
+            label,
+            usage.total_tokens.to_formatted_string(&Locale::en),
+            share,
+            format!("${:.4}", usage.cost),
+            usage.request_count.to_formatted_string(&Locale::en)
+        );
+    }
+}
+
+fn print_profile_usage(usage: &ProfileUsage, options: CheckOptions) {
+    let detailed = options.detailed;
     println!("{} {}", "━━━ Profile:".bold().blue(), usage.name.bold().blue());
     
+    if let Some(reset) = usage.rate_limited_until {
+        println!("  {} Rate limited until {}", "✖".red(), reset.format("%Y-%m-%d %H:%M UTC").to_string().red().bold());
+    }
+    
+    if detailed && usage.duplicates_removed > 0 {
+        println!("  Duplicates skipped: {}", usage.duplicates_removed.to_formatted_string(&Locale::en));
+    }
+    
     if let Some(ref block) = usage.active_block {
         println!("  {} Active Block", "●".green());
         println!("  Started: {}", block.start_time.format("%Y-%m-%d %H:%M:%S UTC"));
         
         // Time remaining
         let now = Utc::now();
-        let end_time = block.start_time + Duration::hours(5);
-        if now < end_time {
-            let remaining = end_time - now;
+        if now < block.end_time {
+            let remaining = block.end_time - now;
             let hours = remaining.num_hours();
             let minutes = remaining.num_minutes() % 60;
             println!("  Remaining: {}h {}m", hours, minutes);
@@ -394,14 +1022,41 @@ fn print_profile_usage(usage: &ProfileUsage, detailed: bool) {
         println!("    Output: {}", block.output_tokens.to_formatted_string(&Locale::en));
         if block.cache_creation_tokens > 0 {
             println!("    Cache+: {}", block.cache_creation_tokens.to_formatted_string(&Locale::en));
+            if block.cache_creation_1h_tokens > 0 {
+                println!(
+                    "      5m: {}  1h: {}",
+                    block.cache_creation_5m_tokens.to_formatted_string(&Locale::en),
+                    block.cache_creation_1h_tokens.to_formatted_string(&Locale::en)
+                );
+            }
         }
         if block.cache_read_tokens > 0 {
             println!("    Cache-: {}", block.cache_read_tokens.to_formatted_string(&Locale::en));
         }
         println!("    {}: {}", "Total".bold(), block.total_tokens.to_formatted_string(&Locale::en));
         
+        if block.web_search_requests > 0 || block.web_fetch_requests > 0 {
+            println!("\n  {}:", "Server Tools".bold());
+            println!("    Web search: {}", block.web_search_requests.to_formatted_string(&Locale::en));
+            println!("    Web fetch:  {}", block.web_fetch_requests.to_formatted_string(&Locale::en));
+        }
+        
         println!("\n  {}: ${:.6}", "Cost".bold(), block.total_cost);
         
+        if detailed && !block.model_breakdown.is_empty() {
+            print_model_breakdown(block);
+        }
+        
+        if detailed && block.cache.prompt_tokens > 0 {
+            println!("\n  {}:", "Cache Efficiency".bold());
+            print_cache_efficiency(&block.cache, "    ");
+        }
+        
+        // Only worth a section once a subagent has run
+        if block.threads.subagent.request_count > 0 {
+            print_thread_split(block);
+        }
+        
         // Burn rate and projections
         if detailed {
             if let Some(ref burn_rate) = block.burn_rate {
@@ -411,9 +1066,9 @@ fn print_profile_usage(usage: &ProfileUsage, detailed: bool) {
                 
                 // Time until limit
                 if let Some(ref time_limit) = burn_rate.time_until_limit {
-                    let color = if time_limit.minutes < 60 {
+                    let color = if time_limit.minutes < options.critical_minutes {
                         "red"
-                    } else if time_limit.minutes < 180 {
+                    } else if time_limit.minutes < options.warn_minutes {
                         "yellow" 
                     } else {
                         "green"
@@ -427,19 +1082,30 @@ fn print_profile_usage(usage: &ProfileUsage, detailed: bool) {
                     
                     println!("\n  {}:", "Time Until Limit".bold());
                     println!("    {}", time_str);
-                    println!("    ({:.1}% of limit used)", 
-                        (block.total_tokens as f64 / models::CLAUDE_TOKEN_LIMIT as f64) * 100.0);
                 }
                 
-                println!("\n  {}:", "Projected (5h)".bold());
+                if let Some(percent) = usage.limit_used_percent {
+                    println!("    ({:.1}% of {} limit used)", percent, usage.limit.label());
+                }
+                
+                if let Some(ref observed) = usage.limit.observed_max {
+                    println!("    Limit: {} tokens, set by {} block starting {}{}",
+                        observed.total_tokens.to_formatted_string(&Locale::en),
+                        observed.profile,
+                        observed.start_time.format("%Y-%m-%d %H:%M UTC"),
+                        if observed.hit_limit { " (hit the limit)" } else { "" });
+                }
+                
+                let block_hours = (block.end_time - block.start_time).num_hours();
+                println!("\n  {}:", format!("Projected ({}h)", block_hours).bold());
                 println!("    Tokens: {}", burn_rate.projected_tokens.to_formatted_string(&Locale::en));
                 println!("    Cost:   ${:.4}", burn_rate.projected_cost);
             }
         }
-        
-        println!();
     } else {
         println!("  {}", "No active block".yellow());
-        println!();
     }
+    
+    print_unknown_models(&usage.unknown_models, "  ");
+    println!();
 }
\ No newline at end of file
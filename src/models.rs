@@ -1,6 +1,5 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 // Claude Code usage limits (tokens per 5-hour block)
 pub const CLAUDE_TOKEN_LIMIT: u64 = 300_000_000; // 300M tokens per 5 hours
@@ -17,13 +16,47 @@ pub struct RawUsageEntry {
     #[serde(rename = "requestId")]
     pub request_id: Option<String>,
     pub model: Option<String>,
+    #[allow(dead_code)]
     pub version: Option<String>,
+    /// Working directory of the session
+    pub cwd: Option<String>,
+    #[serde(rename = "sessionId")]
+    pub session_id: Option<String>,
+    /// Branch checked out in the working directory, empty outside git
+    #[serde(rename = "gitBranch")]
+    pub git_branch: Option<String>,
+    /// Set on requests made by a Task-tool subagent
+    #[serde(rename = "isSidechain", default)]
+    pub is_sidechain: bool,
+    /// Subagent that made the request, on newer Claude Code versions
+    #[serde(rename = "agentId")]
+    pub agent_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct MessageData {
     pub usage: Usage,
     pub model: Option<String>,
+    pub id: Option<String>,
+    pub content: Option<MessageContent>,
+}
+
+/// Message content, either plain text or a list of blocks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    #[allow(dead_code)]
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+/// One block of message content; only tool calls are of interest
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentBlock {
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    pub id: Option<String>,
+    pub name: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -34,39 +67,269 @@ pub struct Usage {
     pub cache_creation_input_tokens: u64,
     #[serde(default)]
     pub cache_read_input_tokens: u64,
+    /// Cache writes split by lifetime, in newer logs
+    pub cache_creation: Option<CacheCreation>,
+    /// Requests to server-side tools, in newer logs
+    pub server_tool_use: Option<ServerToolUse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheCreation {
+    #[serde(default)]
+    pub ephemeral_5m_input_tokens: u64,
+    #[serde(default)]
+    pub ephemeral_1h_input_tokens: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerToolUse {
+    #[serde(default)]
+    pub web_search_requests: u64,
+    #[serde(default)]
+    pub web_fetch_requests: u64,
 }
 
 /// Processed usage entry
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UsageEntry {
     pub timestamp: DateTime<Utc>,
     pub input_tokens: u64,
     pub output_tokens: u64,
+    /// All cache writes, the sum of the 5-minute and 1-hour ones
     pub cache_creation_tokens: u64,
+    pub cache_creation_5m_tokens: u64,
+    pub cache_creation_1h_tokens: u64,
     pub cache_read_tokens: u64,
     pub total_tokens: u64,
+    /// Server-side tool requests, billed per request on top of tokens
+    pub web_search_requests: u64,
+    pub web_fetch_requests: u64,
+    /// Set from the pricing table after loading, see `PricingTable::apply`
     pub cost: f64,
+    /// Cost Claude Code wrote to the log (`costUSD`), if any
+    pub logged_cost: Option<f64>,
+    /// Whether the request fell in a long-context pricing tier, set along
+    /// with `cost`
+    pub premium_tier: bool,
+    /// What the prompt cost at the table's rates, and what it would have
+    /// cost with every prompt token billed as input, set along with `cost`
+    pub prompt_cost: f64,
+    pub uncached_prompt_cost: f64,
+    /// Cache writes the conversation's next request did not read back, set
+    /// after loading
+    pub unread_cache_write_tokens: u64,
     pub model: String,
+    pub message_id: Option<String>,
+    pub request_id: Option<String>,
+    /// Project directory decoded from `projects/<encoded-cwd>/`, set after
+    /// loading. Lossy, since the encoding turns every `/`, `.` and `-`
+    /// into `-`.
+    pub project: Option<String>,
+    /// Working directory recorded on the line itself
+    pub cwd: Option<String>,
+    /// Claude Code session, falling back to the transcript's file name
+    pub session_id: Option<String>,
+    pub git_branch: Option<String>,
+    /// Made by a Task-tool subagent rather than the main conversation
+    pub is_sidechain: bool,
+    /// Subagent that made the request, falling back to its `agent-*`
+    /// transcript's file name
+    pub agent_id: Option<String>,
+    /// Tools the response called, in order
+    pub tools: Vec<ToolCall>,
+}
+
+/// A `tool_use` block of an assistant message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: Option<String>,
+    pub name: String,
+}
+
+/// A "usage limit reached" message written by Claude Code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LimitEvent {
+    pub timestamp: DateTime<Utc>,
+    /// When the limit resets, if the message said
+    pub reset_time: Option<DateTime<Utc>>,
+    pub message: String,
 }
 
 /// Session block (5-hour billing period)
 #[derive(Debug, Clone, Serialize)]
 pub struct SessionBlock {
     pub start_time: DateTime<Utc>,
+    /// End of the 5-hour window
     pub end_time: DateTime<Utc>,
+    /// Timestamp of the last entry in the block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_end_time: Option<DateTime<Utc>>,
     pub is_active: bool,
+    /// Idle period between two blocks, with no entries
+    pub is_gap: bool,
     pub input_tokens: u64,
     pub output_tokens: u64,
     pub cache_creation_tokens: u64,
+    pub cache_creation_5m_tokens: u64,
+    pub cache_creation_1h_tokens: u64,
     pub cache_read_tokens: u64,
     pub total_tokens: u64,
+    pub web_search_requests: u64,
+    pub web_fetch_requests: u64,
     pub total_cost: f64,
     pub models: Vec<String>,
+    /// Tokens, cost and requests of each model, most expensive first
+    pub model_breakdown: Vec<ModelUsage>,
+    /// Usage of the main conversation and of its subagents
+    pub threads: ThreadSplit,
+    pub cache: CacheEfficiency,
     pub entry_count: usize,
+    /// Usage limit messages logged during the block
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub limit_events: Vec<LimitEvent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub burn_rate: Option<BurnRate>,
 }
 
+/// Usage of one model within a block
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub total_tokens: u64,
+    pub cost: f64,
+    pub request_count: usize,
+}
+
+impl ModelUsage {
+    pub fn add(&mut self, entry: &UsageEntry) {
+        self.input_tokens += entry.input_tokens;
+        self.output_tokens += entry.output_tokens;
+        self.cache_creation_tokens += entry.cache_creation_tokens;
+        self.cache_read_tokens += entry.cache_read_tokens;
+        self.total_tokens += entry.total_tokens;
+        self.cost += entry.cost;
+        self.request_count += 1;
+    }
+}
+
+/// Tokens, cost and requests of one side of a `ThreadSplit`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ThreadUsage {
+    pub total_tokens: u64,
+    pub cost: f64,
+    pub request_count: usize,
+}
+
+/// Usage split between the main conversation and Task-tool subagents
+/// (sidechains)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ThreadSplit {
+    pub main: ThreadUsage,
+    pub subagent: ThreadUsage,
+}
+
+impl ThreadSplit {
+    pub fn add(&mut self, entry: &UsageEntry) {
+        let thread = if entry.is_sidechain { &mut self.subagent } else { &mut self.main };
+        thread.total_tokens += entry.total_tokens;
+        thread.cost += entry.cost;
+        thread.request_count += 1;
+    }
+    
+    pub fn merge(&mut self, other: &ThreadSplit) {
+        for (thread, other) in [(&mut self.main, &other.main), (&mut self.subagent, &other.subagent)] {
+            thread.total_tokens += other.total_tokens;
+            thread.cost += other.cost;
+            thread.request_count += other.request_count;
+        }
+    }
+    
+    /// Percentage of the tokens used by subagents
+    pub fn subagent_share(&self) -> f64 {
+        let total = self.main.total_tokens + self.subagent.total_tokens;
+        if total > 0 {
+            self.subagent.total_tokens as f64 / total as f64 * 100.0
+        } else {
+            0.0
+        }
+    }
+    
+    /// Subagent share for a table column, "-" when no subagent ran
+    pub fn subagent_label(&self) -> String {
+        if self.subagent.request_count > 0 {
+            format!("{:.1}%", self.subagent_share())
+        } else {
+            "-".to_string()
+        }
+    }
+}
+
+/// How well prompt caching paid off for a group of requests
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheEfficiency {
+    /// Input, cache write and cache read tokens
+    pub prompt_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+    /// Percentage of the prompt tokens read from the cache
+    pub hit_ratio: f64,
+    /// What the prompts cost, and would have cost without caching
+    pub prompt_cost: f64,
+    pub uncached_prompt_cost: f64,
+    /// USD per million prompt tokens, actual and without caching
+    pub cost_per_million: f64,
+    pub uncached_cost_per_million: f64,
+    /// Negative when cache writes cost more than the reads saved
+    pub savings: f64,
+    /// Cache writes never read back, as far as the logs show
+    pub unread_write_tokens: u64,
+}
+
+impl CacheEfficiency {
+    pub fn add(&mut self, entry: &UsageEntry) {
+        self.prompt_tokens += entry.prompt_tokens();
+        self.cache_read_tokens += entry.cache_read_tokens;
+        self.cache_write_tokens += entry.cache_creation_tokens;
+        self.prompt_cost += entry.prompt_cost;
+        self.uncached_prompt_cost += entry.uncached_prompt_cost;
+        self.unread_write_tokens += entry.unread_cache_write_tokens;
+        self.update_ratios();
+    }
+    
+    pub fn merge(&mut self, other: &CacheEfficiency) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+        self.prompt_cost += other.prompt_cost;
+        self.uncached_prompt_cost += other.uncached_prompt_cost;
+        self.unread_write_tokens += other.unread_write_tokens;
+        self.update_ratios();
+    }
+    
+    fn update_ratios(&mut self) {
+        if self.prompt_tokens > 0 {
+            let millions = self.prompt_tokens as f64 / 1_000_000.0;
+            self.hit_ratio = self.cache_read_tokens as f64 / self.prompt_tokens as f64 * 100.0;
+            self.cost_per_million = self.prompt_cost / millions;
+            self.uncached_cost_per_million = self.uncached_prompt_cost / millions;
+        }
+        self.savings = self.uncached_prompt_cost - self.prompt_cost;
+    }
+    
+    /// Hit ratio for a table column, "-" without prompt tokens
+    pub fn hit_label(&self) -> String {
+        if self.prompt_tokens > 0 {
+            format!("{:.1}%", self.hit_ratio)
+        } else {
+            "-".to_string()
+        }
+    }
+}
+
 /// Burn rate and projections
 #[derive(Debug, Clone, Serialize)]
 pub struct BurnRate {
@@ -86,117 +349,224 @@ pub struct TimeUntilLimit {
     pub human_readable: String,
 }
 
-/// Model pricing information
-#[derive(Debug, Clone)]
+impl TimeUntilLimit {
+    pub fn from_minutes(minutes: u64) -> Self {
+        Self {
+            minutes,
+            human_readable: format_minutes(minutes),
+        }
+    }
+}
+
+/// Directory name Claude Code stores a working directory's transcripts
+/// under, e.g. `/home/me/my.repo` -> `-home-me-my-repo`
+pub fn encode_project_dir(path: &str) -> String {
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Best guess at the working directory behind a project directory name
+pub fn decode_project_dir(name: &str) -> String {
+    name.replace('-', "/")
+}
+
+/// Format a number of minutes as "2h 5m" or "45m"
+pub fn format_minutes(minutes: u64) -> String {
+    let hours = minutes / 60;
+    let mins = minutes % 60;
+    if hours > 0 {
+        format!("{}h {}m", hours, mins)
+    } else {
+        format!("{}m", mins)
+    }
+}
+
+/// Model pricing information, in USD per million tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ModelPricing {
+    #[serde(rename = "input")]
     pub input_cost_per_million: f64,
+    #[serde(rename = "output")]
     pub output_cost_per_million: f64,
+    /// 5-minute cache writes
+    #[serde(rename = "cache_write")]
     pub cache_creation_cost_per_million: f64,
+    /// 1-hour cache writes, twice the input rate when not given
+    #[serde(rename = "cache_write_1h", default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_1h_cost_per_million: Option<f64>,
+    #[serde(rename = "cache_read")]
     pub cache_read_cost_per_million: f64,
+    /// USD per web search request, $10 per thousand when not given
+    #[serde(rename = "web_search", default, skip_serializing_if = "Option::is_none")]
+    pub web_search_cost_per_request: Option<f64>,
+    /// USD per web fetch request, free (only its tokens are billed) when
+    /// not given
+    #[serde(rename = "web_fetch", default, skip_serializing_if = "Option::is_none")]
+    pub web_fetch_cost_per_request: Option<f64>,
+    /// Higher rates for requests with a long prompt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<PricingTier>,
 }
 
 impl Default for ModelPricing {
     fn default() -> Self {
-        // Default Claude pricing (you can update these)
+        // Sonnet pricing, for when even the pricing table's fallback is missing
         Self {
             input_cost_per_million: 3.0,    // $3 per million tokens
             output_cost_per_million: 15.0,  // $15 per million tokens
             cache_creation_cost_per_million: 3.75,
+            cache_creation_1h_cost_per_million: Some(6.0),
             cache_read_cost_per_million: 0.30,
+            web_search_cost_per_request: None,
+            web_fetch_cost_per_request: None,
+            tiers: Vec::new(),
         }
     }
 }
 
-/// Get pricing for a specific model
-pub fn get_model_pricing(model: &str) -> ModelPricing {
-    let pricing_map: HashMap<&str, ModelPricing> = HashMap::from([
-        ("claude-3-5-sonnet-20241022", ModelPricing {
-            input_cost_per_million: 3.0,
-            output_cost_per_million: 15.0,
-            cache_creation_cost_per_million: 3.75,
-            cache_read_cost_per_million: 0.30,
-        }),
-        ("claude-3-5-sonnet-20240620", ModelPricing {
-            input_cost_per_million: 3.0,
-            output_cost_per_million: 15.0,
-            cache_creation_cost_per_million: 3.75,
-            cache_read_cost_per_million: 0.30,
-        }),
-        ("claude-3-5-haiku-20241022", ModelPricing {
-            input_cost_per_million: 1.0,
-            output_cost_per_million: 5.0,
-            cache_creation_cost_per_million: 1.25,
-            cache_read_cost_per_million: 0.10,
-        }),
-        ("claude-3-opus-20240229", ModelPricing {
-            input_cost_per_million: 15.0,
-            output_cost_per_million: 75.0,
-            cache_creation_cost_per_million: 18.75,
-            cache_read_cost_per_million: 1.50,
-        }),
-        ("claude-3-sonnet-20240229", ModelPricing {
-            input_cost_per_million: 3.0,
-            output_cost_per_million: 15.0,
-            cache_creation_cost_per_million: 3.75,
-            cache_read_cost_per_million: 0.30,
-        }),
-        ("claude-3-haiku-20240307", ModelPricing {
-            input_cost_per_million: 0.25,
-            output_cost_per_million: 1.25,
-            cache_creation_cost_per_million: 0.30,
-            cache_read_cost_per_million: 0.03,
-        }),
-        // Claude 4 models
-        ("claude-sonnet-4-20250514", ModelPricing {
-            input_cost_per_million: 3.0,
-            output_cost_per_million: 15.0,
-            cache_creation_cost_per_million: 3.75,
-            cache_read_cost_per_million: 0.30,
-        }),
-        ("claude-opus-4-20250514", ModelPricing {
-            input_cost_per_million: 15.0,
-            output_cost_per_million: 75.0,
-            cache_creation_cost_per_million: 18.75,
-            cache_read_cost_per_million: 1.50,
-        }),
-    ]);
+impl ModelPricing {
+    /// The tier for a request whose prompt (input plus cache tokens) has
+    /// `prompt_tokens`, if it goes over any tier's threshold
+    pub fn tier_for(&self, prompt_tokens: u64) -> Option<&PricingTier> {
+        self.tiers
+            .iter()
+            .filter(|tier| prompt_tokens > tier.above_input_tokens)
+            .max_by_key(|tier| tier.above_input_tokens)
+    }
     
-    // Try exact match first
-    if let Some(pricing) = pricing_map.get(model) {
-        return pricing.clone();
+    pub fn web_search_rate(&self) -> f64 {
+        self.web_search_cost_per_request.unwrap_or(0.01)
     }
     
-    // Try to match by model family
-    if model.contains("opus") {
-        ModelPricing {
-            input_cost_per_million: 15.0,
-            output_cost_per_million: 75.0,
-            cache_creation_cost_per_million: 18.75,
-            cache_read_cost_per_million: 1.50,
-        }
-    } else if model.contains("haiku") {
-        ModelPricing {
-            input_cost_per_million: 0.25,
-            output_cost_per_million: 1.25,
-            cache_creation_cost_per_million: 0.30,
-            cache_read_cost_per_million: 0.03,
+    pub fn web_fetch_rate(&self) -> f64 {
+        self.web_fetch_cost_per_request.unwrap_or(0.0)
+    }
+    
+    /// Rates for a request with `prompt_tokens`, from its tier if it has one
+    pub fn rates_for(&self, prompt_tokens: u64) -> TokenRates {
+        match self.tier_for(prompt_tokens) {
+            Some(tier) => TokenRates {
+                input: tier.input_cost_per_million,
+                output: tier.output_cost_per_million,
+                cache_write_5m: tier.cache_creation_cost_per_million,
+                cache_write_1h: tier.cache_creation_1h_cost_per_million.unwrap_or(tier.input_cost_per_million * 2.0),
+                cache_read: tier.cache_read_cost_per_million,
+            },
+            None => TokenRates {
+                input: self.input_cost_per_million,
+                output: self.output_cost_per_million,
+                cache_write_5m: self.cache_creation_cost_per_million,
+                cache_write_1h: self.cache_creation_1h_cost_per_million.unwrap_or(self.input_cost_per_million * 2.0),
+                cache_read: self.cache_read_cost_per_million,
+            },
         }
-    } else {
-        // Default to Sonnet pricing
-        ModelPricing::default()
     }
 }
 
+/// Per-million-token rates that apply to one request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenRates {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write_5m: f64,
+    pub cache_write_1h: f64,
+    pub cache_read: f64,
+}
+
+/// Rates for the whole request once its prompt exceeds `above_input_tokens`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PricingTier {
+    pub above_input_tokens: u64,
+    #[serde(rename = "input")]
+    pub input_cost_per_million: f64,
+    #[serde(rename = "output")]
+    pub output_cost_per_million: f64,
+    /// 5-minute cache writes
+    #[serde(rename = "cache_write")]
+    pub cache_creation_cost_per_million: f64,
+    /// 1-hour cache writes, twice the input rate when not given
+    #[serde(rename = "cache_write_1h", default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_1h_cost_per_million: Option<f64>,
+    #[serde(rename = "cache_read")]
+    pub cache_read_cost_per_million: f64,
+}
+
 impl UsageEntry {
-    /// Calculate cost for this entry
-    pub fn calculate_cost(&self) -> f64 {
-        let pricing = get_model_pricing(&self.model);
+    /// Size of the request's prompt, which decides its pricing tier
+    pub fn prompt_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_tokens + self.cache_read_tokens
+    }
+    
+    /// Calculate cost for this entry at the given prices, using the
+    /// long-context tier when the prompt is large enough
+    pub fn calculate_cost(&self, pricing: &ModelPricing) -> f64 {
+        let rates = pricing.rates_for(self.prompt_tokens());
+        let output_cost = (self.output_tokens as f64 / 1_000_000.0) * rates.output;
         
-        let input_cost = (self.input_tokens as f64 / 1_000_000.0) * pricing.input_cost_per_million;
-        let output_cost = (self.output_tokens as f64 / 1_000_000.0) * pricing.output_cost_per_million;
-        let cache_creation_cost = (self.cache_creation_tokens as f64 / 1_000_000.0) * pricing.cache_creation_cost_per_million;
-        let cache_read_cost = (self.cache_read_tokens as f64 / 1_000_000.0) * pricing.cache_read_cost_per_million;
+        self.prompt_cost_at(&rates) + output_cost + self.server_tool_cost(pricing)
+    }
+    
+    /// What the prompt costs at the given prices, and what it would cost
+    /// if none of it were cached
+    pub fn prompt_costs(&self, pricing: &ModelPricing) -> (f64, f64) {
+        let rates = pricing.rates_for(self.prompt_tokens());
+        let uncached = (self.prompt_tokens() as f64 / 1_000_000.0) * rates.input;
+        (self.prompt_cost_at(&rates), uncached)
+    }
+    
+    fn prompt_cost_at(&self, rates: &TokenRates) -> f64 {
+        let input_cost = (self.input_tokens as f64 / 1_000_000.0) * rates.input;
+        let cache_creation_cost = (self.cache_creation_5m_tokens as f64 / 1_000_000.0) * rates.cache_write_5m
+            + (self.cache_creation_1h_tokens as f64 / 1_000_000.0) * rates.cache_write_1h;
+        let cache_read_cost = (self.cache_read_tokens as f64 / 1_000_000.0) * rates.cache_read;
         
-        input_cost + output_cost + cache_creation_cost + cache_read_cost
+        input_cost + cache_creation_cost + cache_read_cost
     }
-}
\ No newline at end of file
+    
+    /// Cost of the request's server-side tool calls, which no pricing tier
+    /// changes
+    pub fn server_tool_cost(&self, pricing: &ModelPricing) -> f64 {
+        self.web_search_requests as f64 * pricing.web_search_rate()
+            + self.web_fetch_requests as f64 * pricing.web_fetch_rate()
+    }
+
+    /// Best available project path: the logged working directory when it
+    /// belongs to the entry's project directory, else the decoded directory
+    pub fn project_path(&self) -> &str {
+        match (&self.cwd, &self.project) {
+            (Some(cwd), Some(project)) if encode_project_dir(cwd) == encode_project_dir(project) => cwd,
+            (_, Some(project)) => project,
+            (Some(cwd), None) => cwd,
+            (None, None) => "(unknown)",
+        }
+    }
+    
+    /// Key used to detect the same API response logged in several files.
+    /// Entries missing either ID are never treated as duplicates.
+    pub fn dedup_key(&self) -> Option<(&str, &str)> {
+        match (&self.message_id, &self.request_id) {
+            (Some(message_id), Some(request_id)) => Some((message_id, request_id)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl UsageEntry {
+    /// An empty entry at an RFC 3339 timestamp, for tests to fill in
+    pub fn test_at(timestamp: &str) -> Self {
+        UsageEntry {
+            timestamp: test_time(timestamp),
+            ..Default::default()
+        }
+    }
+}
+
+/// Parse an RFC 3339 timestamp in tests
+#[cfg(test)]
+pub fn test_time(timestamp: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc)
+}
@@ -1,15 +1,29 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use rayon::prelude::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+use crate::cache::{fingerprint, CachedFile, ParseCache};
 use crate::models::*;
 
-/// Load all usage entries from a projects directory
-pub fn load_usage_entries(projects_dir: &Path) -> Result<Vec<UsageEntry>> {
+/// Usage entries loaded from a projects directory
+#[derive(Debug, Default)]
+pub struct LoadedUsage {
+    pub entries: Vec<UsageEntry>,
+    /// Entries dropped because the same message/request was already seen
+    pub duplicates_removed: usize,
+    /// Usage limit messages, sorted by timestamp
+    pub limit_events: Vec<LimitEvent>,
+}
+
+/// Load all usage entries from a projects directory.
+/// With `use_cache`, files are only re-parsed from where the last run stopped.
+pub fn load_usage_entries(projects_dir: &Path, use_cache: bool) -> Result<LoadedUsage> {
     // Collect all JSONL file paths first
     let jsonl_files: Vec<_> = WalkDir::new(projects_dir)
         .into_iter()
@@ -21,47 +35,281 @@ pub fn load_usage_entries(projects_dir: &Path) -> Result<Vec<UsageEntry>> {
         .map(|e| e.path().to_path_buf())
         .collect();
     
+    let mut cache = if use_cache {
+        ParseCache::load(projects_dir)
+    } else {
+        ParseCache::default()
+    };
+    
+    // Pair each file with its previous parse state; whatever is left over
+    // belongs to files that have since been deleted
+    let mut previous = std::mem::take(&mut cache.files);
+    let work: Vec<(PathBuf, Option<CachedFile>)> = jsonl_files
+        .into_iter()
+        .map(|path| {
+            let cached = previous.remove(&path);
+            (path, cached)
+        })
+        .collect();
+    let mut changed = !previous.is_empty();
+    
     // Process files in parallel
-    let mut entries: Vec<UsageEntry> = jsonl_files
-        .par_iter()
-        .filter_map(|path| load_jsonl_file(path).ok())
-        .flatten()
+    let scanned: Vec<(PathBuf, CachedFile, bool)> = work
+        .into_par_iter()
+        .filter_map(|(path, cached)| {
+            scan_jsonl_file(&path, cached)
+                .ok()
+                .map(|(file, updated)| (path, file, updated))
+        })
+        .collect();
+    
+    let mut entries: Vec<UsageEntry> = scanned
+        .iter()
+        .flat_map(|(path, file, _)| {
+            let project = project_of(projects_dir, path);
+            // Each transcript is one session, named after the file
+            let session = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string);
+            // Subagents get their own agent-<id> transcripts
+            let agent = session.as_deref().and_then(|stem| stem.strip_prefix("agent-")).map(str::to_string);
+            file.entries.iter().cloned().map(move |mut entry| {
+                entry.project = project.clone();
+                if entry.session_id.is_none() {
+                    entry.session_id = session.clone();
+                }
+                if entry.is_sidechain && entry.agent_id.is_none() {
+                    entry.agent_id = agent.clone();
+                }
+                entry
+            })
+        })
+        .collect();
+    let mut limit_events: Vec<LimitEvent> = scanned
+        .iter()
+        .flat_map(|(_, file, _)| file.limit_events.iter().cloned())
         .collect();
     
+    if use_cache {
+        changed |= scanned.iter().any(|(_, _, updated)| *updated);
+        if changed {
+            cache.files = scanned
+                .into_iter()
+                .map(|(path, file, _)| (path, file))
+                .collect();
+            // A cache that cannot be written only costs speed next time
+            let _ = cache.save(projects_dir);
+        }
+    }
+    
     // Sort by timestamp
-    entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    entries.sort_by_key(|e| e.timestamp);
+    
+    // Resumed or branched conversations copy the same messages into
+    // several session files, so keep only the earliest copy of each
+    let duplicates_removed = deduplicate_entries(&mut entries);
+    mark_unread_cache_writes(&mut entries);
+    
+    // Limit messages get copied along with the conversation too
+    limit_events.sort_by(|a, b| {
+        a.timestamp
+            .cmp(&b.timestamp)
+            .then_with(|| a.message.cmp(&b.message))
+            .then(a.reset_time.cmp(&b.reset_time))
+    });
+    limit_events.dedup();
     
-    Ok(entries)
+    Ok(LoadedUsage {
+        entries,
+        duplicates_removed,
+        limit_events,
+    })
 }
 
-/// Load entries from a single JSONL file
-fn load_jsonl_file(path: &Path) -> Result<Vec<UsageEntry>> {
-    let file = File::open(path).context("Failed to open JSONL file")?;
-    let reader = BufReader::with_capacity(64 * 1024, file); // 64KB buffer
+/// Decoded project directory a transcript sits under, i.e. the first
+/// directory below `projects/`
+fn project_of(projects_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(projects_dir).ok()?;
+    let mut components = relative.components();
+    let project = components.next()?;
+    // A transcript directly in projects/ has no project
+    components.next()?;
+    Some(decode_project_dir(project.as_os_str().to_str()?))
+}
+
+/// Set how much of each entry's cache writes went unread. A conversation
+/// (a session's main thread, or one of its subagents) reads its earlier
+/// writes as the growing prefix of its next prompt, so the writes count as
+/// read up to how far the next request's cache reads got past this one's,
+/// if it came within the cache lifetime. Subagents logged without an agent
+/// ID share one conversation per session. Expects entries sorted by
+/// timestamp.
+fn mark_unread_cache_writes(entries: &mut [UsageEntry]) {
+    let mut previous: HashMap<(Option<&str>, bool, Option<&str>), usize> = HashMap::new();
+    let mut next = vec![None; entries.len()];
+    for (index, entry) in entries.iter().enumerate() {
+        let conversation = (entry.session_id.as_deref(), entry.is_sidechain, entry.agent_id.as_deref());
+        if let Some(before) = previous.insert(conversation, index) {
+            next[before] = Some(index);
+        }
+    }
+    
+    for index in 0..entries.len() {
+        let entry = &entries[index];
+        let lifetime = if entry.cache_creation_1h_tokens > 0 {
+            Duration::hours(1)
+        } else {
+            Duration::minutes(5)
+        };
+        let read_back = next[index]
+            .map(|next| &entries[next])
+            .filter(|next| next.timestamp - entry.timestamp <= lifetime)
+            .map_or(0, |next| next.cache_read_tokens.saturating_sub(entry.cache_read_tokens));
+        let unread = entry.cache_creation_tokens - read_back.min(entry.cache_creation_tokens);
+        entries[index].unread_cache_write_tokens = unread;
+    }
+}
+
+/// Remove entries whose message/request ID pair was already seen.
+/// Expects entries sorted by timestamp and returns the number removed.
+///
+/// Claude Code logs a response with several content blocks as one line per
+/// block, each repeating the usage, so the tool calls of the dropped copies
+/// are folded into the kept entry.
+fn deduplicate_entries(entries: &mut Vec<UsageEntry>) -> usize {
+    let before = entries.len();
+    let mut kept: Vec<UsageEntry> = Vec::with_capacity(entries.len());
+    let mut seen: HashMap<String, usize> = HashMap::with_capacity(entries.len());
+    
+    for entry in entries.drain(..) {
+        let Some((message_id, request_id)) = entry.dedup_key() else {
+            kept.push(entry);
+            continue;
+        };
+        match seen.get(&format!("{}:{}", message_id, request_id)) {
+            Some(&index) => {
+                let tools = &mut kept[index].tools;
+                // Copies from resumed sessions repeat calls already seen;
+                // calls without an ID cannot be told apart, so keep the first
+                for call in entry.tools {
+                    if call.id.is_some() && !tools.contains(&call) {
+                        tools.push(call);
+                    }
+                }
+            }
+            None => {
+                seen.insert(format!("{}:{}", message_id, request_id), kept.len());
+                kept.push(entry);
+            }
+        }
+    }
+    
+    *entries = kept;
+    before - entries.len()
+}
+
+/// Bring a file's parse state up to date, reusing the cached state when the
+/// file was only appended to. Returns the new state and whether it changed.
+fn scan_jsonl_file(path: &Path, cached: Option<CachedFile>) -> Result<(CachedFile, bool)> {
+    let metadata = fs::metadata(path).context("Failed to read JSONL metadata")?;
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    
+    let mut entries = Vec::new();
+    let mut limit_events = Vec::new();
+    let mut start = 0;
+    
+    if let Some(cached) = cached {
+        if cached.len == len && cached.modified == modified {
+            return Ok((cached, false));
+        }
+        
+        // Anything shorter than what we parsed, or with a different head,
+        // was truncated or rotated and has to be parsed from scratch
+        if len >= cached.offset && fingerprint(path, cached.offset)? == cached.fingerprint {
+            start = cached.offset;
+            entries = cached.entries;
+            limit_events = cached.limit_events;
+        }
+    }
+    
+    let chunk = load_jsonl_file(path, start)?;
+    entries.extend(chunk.entries);
+    limit_events.extend(chunk.limit_events);
+    let offset = chunk.offset;
+    
+    Ok((
+        CachedFile {
+            modified,
+            len,
+            offset,
+            fingerprint: fingerprint(path, offset)?,
+            entries,
+            limit_events,
+        },
+        true,
+    ))
+}
+
+/// Records parsed from part of a JSONL file
+struct FileChunk {
+    entries: Vec<UsageEntry>,
+    limit_events: Vec<LimitEvent>,
+    /// Offset just past the last complete line
+    offset: u64,
+}
+
+/// Load entries from a single JSONL file starting at byte `start`.
+/// Stops after the last complete line, so a line still being written is
+/// picked up on the next scan.
+fn load_jsonl_file(path: &Path, start: u64) -> Result<FileChunk> {
+    let mut file = File::open(path).context("Failed to open JSONL file")?;
+    file.seek(SeekFrom::Start(start)).context("Failed to seek JSONL file")?;
+    let mut reader = BufReader::with_capacity(64 * 1024, file); // 64KB buffer
     let mut entries = Vec::with_capacity(1000); // Pre-allocate for typical file size
+    let mut limit_events = Vec::new();
+    let mut offset = start;
+    let mut buf = Vec::new();
     
-    for (line_num, line) in reader.lines().enumerate() {
-        let line = line.context("Failed to read line")?;
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf).context("Failed to read line")?;
+        if read == 0 {
+            break;
+        }
+        
+        let complete = buf.ends_with(b"\n");
+        let line = String::from_utf8_lossy(&buf);
         if line.trim().is_empty() {
+            if complete {
+                offset += read as u64;
+            }
             continue;
         }
         
-        match parse_jsonl_line(&line) {
+        let parsed = parse_jsonl_line(&line);
+        // An unterminated last line is only consumed once it parses
+        if !complete && parsed.is_err() {
+            break;
+        }
+        offset += read as u64;
+        
+        match parsed {
             Ok(Some(entry)) => entries.push(entry),
-            Ok(None) => {}, // Skipped entry
-            Err(e) => {
-                // Just skip invalid lines
+            Ok(None) => {
+                // Skipped entry, but it may announce a usage limit
+                limit_events.extend(parse_limit_event(&line));
+            }
+            Err(_) => {
                 // Silently skip parse errors - they're expected for some entries
-                #[allow(unused_variables)]
-                if false {  // Set to true to debug parsing issues
-                    eprintln!("Warning: Failed to parse line {} in {}: {}", 
-                             line_num + 1, path.display(), e);
-                }
+                limit_events.extend(parse_limit_event(&line));
             }
         }
     }
     
-    Ok(entries)
+    Ok(FileChunk {
+        entries,
+        limit_events,
+        offset,
+    })
 }
 
 /// Parse a single JSONL line into a UsageEntry
@@ -80,38 +328,204 @@ fn parse_jsonl_line(line: &str) -> Result<Option<UsageEntry>> {
         .or(raw.message.model)
         .unwrap_or_else(|| "unknown".to_string());
     
-    // Skip if it's not a real model
-    if model == "unknown" || model.is_empty() {
+    // Skip if it's not a real model (Claude Code logs its own error
+    // messages, such as usage limit notices, as "<synthetic>")
+    if model == "unknown" || model.is_empty() || model == "<synthetic>" {
         return Ok(None);
     }
     
+    // Split cache writes by lifetime. Older logs only have the total,
+    // which was all 5-minute writes.
+    let usage = &raw.message.usage;
+    let (cache_creation_5m_tokens, cache_creation_1h_tokens) = match usage.cache_creation {
+        Some(ref split) => (split.ephemeral_5m_input_tokens, split.ephemeral_1h_input_tokens),
+        None => (usage.cache_creation_input_tokens, 0),
+    };
+    let cache_creation_tokens = usage
+        .cache_creation_input_tokens
+        .max(cache_creation_5m_tokens + cache_creation_1h_tokens);
+    // Whatever the split does not account for is billed as 5-minute writes
+    let cache_creation_5m_tokens = cache_creation_tokens - cache_creation_1h_tokens;
+    
+    let tools = match raw.message.content {
+        Some(MessageContent::Blocks(blocks)) => blocks
+            .into_iter()
+            .filter(|block| block.kind == "tool_use")
+            .map(|block| ToolCall {
+                id: block.id,
+                name: block.name.unwrap_or_else(|| "unknown".to_string()),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    
+    let (web_search_requests, web_fetch_requests) = usage
+        .server_tool_use
+        .as_ref()
+        .map_or((0, 0), |server| (server.web_search_requests, server.web_fetch_requests));
+    
     // Calculate total tokens
-    let total_tokens = raw.message.usage.input_tokens
-        + raw.message.usage.output_tokens
-        + raw.message.usage.cache_creation_input_tokens
-        + raw.message.usage.cache_read_input_tokens;
+    let total_tokens = usage.input_tokens
+        + usage.output_tokens
+        + cache_creation_tokens
+        + usage.cache_read_input_tokens;
     
     // Create processed entry
-    let mut entry = UsageEntry {
+    let entry = UsageEntry {
         timestamp,
-        input_tokens: raw.message.usage.input_tokens,
-        output_tokens: raw.message.usage.output_tokens,
-        cache_creation_tokens: raw.message.usage.cache_creation_input_tokens,
-        cache_read_tokens: raw.message.usage.cache_read_input_tokens,
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cache_creation_tokens,
+        cache_creation_5m_tokens,
+        cache_creation_1h_tokens,
+        cache_read_tokens: usage.cache_read_input_tokens,
         total_tokens,
-        cost: 0.0, // Will calculate below
+        web_search_requests,
+        web_fetch_requests,
+        // Priced after loading, so pricing changes never need a re-parse
+        cost: 0.0,
+        logged_cost: raw.cost_usd,
+        premium_tier: false,
+        prompt_cost: 0.0,
+        uncached_prompt_cost: 0.0,
+        unread_cache_write_tokens: 0,
         model,
+        message_id: raw.message_id.or(raw.message.id),
+        request_id: raw.request_id,
+        project: None,
+        cwd: raw.cwd,
+        session_id: raw.session_id,
+        git_branch: raw.git_branch.filter(|branch| !branch.is_empty()),
+        is_sidechain: raw.is_sidechain,
+        agent_id: raw.agent_id,
+        tools,
     };
     
-    // Use provided cost or calculate
-    entry.cost = raw.cost_usd.unwrap_or_else(|| entry.calculate_cost());
-    
     Ok(Some(entry))
 }
 
+/// Recognise a "usage limit reached" message logged by Claude Code
+fn parse_limit_event(line: &str) -> Option<LimitEvent> {
+    // Cheap check before parsing the JSON
+    if !line.contains("limit reached") {
+        return None;
+    }
+    
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    
+    // Only trust messages Claude Code wrote itself, not conversation text
+    let is_system = value["type"] == "system";
+    let is_api_error = value["isApiErrorMessage"] == true || value["message"]["model"] == "<synthetic>";
+    if !is_system && !is_api_error {
+        return None;
+    }
+    
+    let timestamp = DateTime::parse_from_rfc3339(value["timestamp"].as_str()?)
+        .ok()?
+        .with_timezone(&Utc);
+    
+    let message = message_texts(&value)
+        .into_iter()
+        .find(|text| text.contains("limit reached"))?;
+    
+    Some(LimitEvent {
+        timestamp,
+        reset_time: parse_reset_time(&message, timestamp),
+        message,
+    })
+}
+
+/// Collect the text of a line's content, whether a string or text blocks
+fn message_texts(value: &serde_json::Value) -> Vec<String> {
+    let mut texts = Vec::new();
+    for content in [&value["message"]["content"], &value["content"]] {
+        match content {
+            serde_json::Value::String(text) => texts.push(text.clone()),
+            serde_json::Value::Array(blocks) => texts.extend(
+                blocks.iter().filter_map(|block| block["text"].as_str().map(str::to_string)),
+            ),
+            _ => {}
+        }
+    }
+    texts
+}
+
+/// Extract the reset time from a limit message. Understands the
+/// "usage limit reached|<unix seconds>" form and "resets 2pm",
+/// "reset at 2:30pm (Europe/London)" or "resets 14:00".
+fn parse_reset_time(message: &str, timestamp: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some((_, epoch)) = message.split_once('|') {
+        let seconds: i64 = epoch.trim().parse().ok()?;
+        return DateTime::from_timestamp(seconds, 0);
+    }
+    
+    let lower = message.to_ascii_lowercase();
+    let rest = &lower[lower.find("reset")? + "reset".len()..];
+    let rest = rest.strip_prefix('s').unwrap_or(rest).trim_start();
+    let rest = rest.strip_prefix("at ").unwrap_or(rest).trim_start();
+    
+    // Split "2:30pm (europe/london)" into the time and the optional zone
+    let time_end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ':' || c == 'a' || c == 'p' || c == 'm' || c == ' '))
+        .unwrap_or(rest.len());
+    let time_text = rest[..time_end].trim().replace(' ', "");
+    let time = parse_clock_time(&time_text)?;
+    
+    // Zone names are case-sensitive, so take them from the original text
+    let zone = message[lower.len() - rest.len() + time_end..]
+        .trim_start()
+        .strip_prefix('(')
+        .and_then(|zone| zone.split_once(')'))
+        .and_then(|(zone, _)| zone.trim().parse::<Tz>().ok());
+    
+    match zone {
+        Some(zone) => next_occurrence(&zone, time, timestamp),
+        None => next_occurrence(&Local, time, timestamp),
+    }
+}
+
+/// Parse "2pm", "2:30pm" or "14:00"
+fn parse_clock_time(text: &str) -> Option<NaiveTime> {
+    let (digits, offset) = if let Some(digits) = text.strip_suffix("am") {
+        (digits, 0)
+    } else if let Some(digits) = text.strip_suffix("pm") {
+        (digits, 12)
+    } else {
+        (text, 0)
+    };
+    
+    let (hour, minute) = match digits.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        None => (digits.parse::<u32>().ok()?, 0),
+    };
+    
+    let hour = if text.len() != digits.len() {
+        // 12am is midnight and 12pm is noon
+        if hour == 0 || hour > 12 {
+            return None;
+        }
+        hour % 12 + offset
+    } else {
+        hour
+    };
+    
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// First time after `after` that the wall clock in `zone` shows `time`
+fn next_occurrence<Z: TimeZone>(zone: &Z, time: NaiveTime, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let local_date = after.with_timezone(zone).date_naive();
+    [local_date, local_date + Duration::days(1)]
+        .into_iter()
+        .filter_map(|date| zone.from_local_datetime(&date.and_time(time)).earliest())
+        .map(|reset| reset.with_timezone(&Utc))
+        .find(|reset| *reset > after)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::test_time;
     
     #[test]
     fn test_parse_jsonl_line() {
@@ -126,4 +540,252 @@ mod tests {
         assert_eq!(entry.total_tokens, 150);
         assert_eq!(entry.model, "claude-3-5-sonnet-20241022");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_parse_logged_cost() {
+        let line = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":100,"output_tokens":50}},"costUSD":0.001,"model":"claude-3-5-sonnet-20241022"}"#;
+        let entry = parse_jsonl_line(line).unwrap().unwrap();
+        assert_eq!(entry.logged_cost, Some(0.001));
+        // Priced after loading
+        assert_eq!(entry.cost, 0.0);
+        
+        let unlogged = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":100,"output_tokens":50}},"model":"claude-sonnet-4-5"}"#;
+        assert_eq!(parse_jsonl_line(unlogged).unwrap().unwrap().logged_cost, None);
+    }
+    
+    #[test]
+    fn test_parse_cache_creation_split() {
+        let split = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5,"cache_creation_input_tokens":300,"cache_creation":{"ephemeral_5m_input_tokens":100,"ephemeral_1h_input_tokens":200}}},"model":"claude-sonnet-4-5"}"#;
+        let entry = parse_jsonl_line(split).unwrap().unwrap();
+        assert_eq!(entry.cache_creation_tokens, 300);
+        assert_eq!(entry.cache_creation_5m_tokens, 100);
+        assert_eq!(entry.cache_creation_1h_tokens, 200);
+        assert_eq!(entry.total_tokens, 315);
+        
+        // Older logs only have the total, all of it written for 5 minutes
+        let unsplit = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5,"cache_creation_input_tokens":300}},"model":"claude-sonnet-4-5"}"#;
+        let entry = parse_jsonl_line(unsplit).unwrap().unwrap();
+        assert_eq!(entry.cache_creation_5m_tokens, 300);
+        assert_eq!(entry.cache_creation_1h_tokens, 0);
+    }
+    
+    #[test]
+    fn test_parse_server_tool_use() {
+        let search = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5,"server_tool_use":{"web_search_requests":2}}},"model":"claude-sonnet-4-5"}"#;
+        let entry = parse_jsonl_line(search).unwrap().unwrap();
+        assert_eq!(entry.web_search_requests, 2);
+        assert_eq!(entry.web_fetch_requests, 0);
+        
+        let fetch = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5,"server_tool_use":{"web_search_requests":0,"web_fetch_requests":3}}},"model":"claude-sonnet-4-5"}"#;
+        let entry = parse_jsonl_line(fetch).unwrap().unwrap();
+        assert_eq!(entry.web_search_requests, 0);
+        assert_eq!(entry.web_fetch_requests, 3);
+    }
+    
+    #[test]
+    fn test_project_of() {
+        let projects = Path::new("/home/me/.claude/projects");
+        let project = project_of(projects, &projects.join("-home-me-my-repo/abc.jsonl")).unwrap();
+        assert_eq!(project, "/home/me/my/repo");
+        assert!(project_of(projects, &projects.join("abc.jsonl")).is_none());
+        
+        // The directory name is ambiguous, so the logged cwd spells it out
+        let line = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5}},"cwd":"/home/me/my.repo","model":"claude-sonnet-4-5"}"#;
+        let entry = UsageEntry {
+            project: Some(project),
+            ..parse_jsonl_line(line).unwrap().unwrap()
+        };
+        assert_eq!(entry.cwd.as_deref(), Some("/home/me/my.repo"));
+        assert_eq!(entry.project_path(), "/home/me/my.repo");
+        
+        let entry = UsageEntry { cwd: None, ..entry };
+        assert_eq!(entry.project_path(), "/home/me/my/repo");
+    }
+    
+    #[test]
+    fn test_parse_session_id() {
+        let line = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5}},"sessionId":"3f2a","model":"claude-sonnet-4-5"}"#;
+        assert_eq!(parse_jsonl_line(line).unwrap().unwrap().session_id.as_deref(), Some("3f2a"));
+        
+        // Left for the loader to fill in from the transcript name
+        let unnamed = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5}},"model":"claude-sonnet-4-5"}"#;
+        assert!(parse_jsonl_line(unnamed).unwrap().unwrap().session_id.is_none());
+    }
+    
+    #[test]
+    fn test_parse_git_branch() {
+        let line = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5}},"gitBranch":"feature/x","model":"claude-sonnet-4-5"}"#;
+        assert_eq!(parse_jsonl_line(line).unwrap().unwrap().git_branch.as_deref(), Some("feature/x"));
+        
+        // Claude Code logs an empty branch outside a git repository
+        let outside = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5}},"gitBranch":"","model":"claude-sonnet-4-5"}"#;
+        assert!(parse_jsonl_line(outside).unwrap().unwrap().git_branch.is_none());
+    }
+    
+    #[test]
+    fn test_parse_sidechain_agent() {
+        let line = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5}},"isSidechain":true,"agentId":"a1b2","model":"claude-sonnet-4-5"}"#;
+        let entry = parse_jsonl_line(line).unwrap().unwrap();
+        assert!(entry.is_sidechain);
+        assert_eq!(entry.agent_id.as_deref(), Some("a1b2"));
+        
+        let main = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"usage":{"input_tokens":10,"output_tokens":5}},"model":"claude-sonnet-4-5"}"#;
+        let entry = parse_jsonl_line(main).unwrap().unwrap();
+        assert!(!entry.is_sidechain);
+        assert!(entry.agent_id.is_none());
+    }
+    
+    #[test]
+    fn test_deduplicate_entries() {
+        let line = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"id":"msg_1","usage":{"input_tokens":100,"output_tokens":50}},"requestId":"req_1","model":"claude-3-5-sonnet-20241022"}"#;
+        let other = r#"{"timestamp":"2024-01-01T10:05:00Z","message":{"id":"msg_2","usage":{"input_tokens":100,"output_tokens":50}},"requestId":"req_2","model":"claude-3-5-sonnet-20241022"}"#;
+        let no_ids = r#"{"timestamp":"2024-01-01T10:10:00Z","message":{"usage":{"input_tokens":100,"output_tokens":50}},"model":"claude-3-5-sonnet-20241022"}"#;
+        
+        let mut entries: Vec<UsageEntry> = [line, line, other, no_ids, no_ids]
+            .iter()
+            .map(|l| parse_jsonl_line(l).unwrap().unwrap())
+            .collect();
+        
+        assert_eq!(deduplicate_entries(&mut entries), 1);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].message_id.as_deref(), Some("msg_1"));
+        assert_eq!(entries[1].message_id.as_deref(), Some("msg_2"));
+        
+        // One line per content block of the same response
+        let text = r#"{"timestamp":"2024-01-01T10:00:00Z","message":{"id":"msg_3","content":[{"type":"text","text":"Let me look"}],"usage":{"input_tokens":100,"output_tokens":50}},"requestId":"req_3","model":"claude-sonnet-4"}"#;
+        let read = r#"{"timestamp":"2024-01-01T10:00:01Z","message":{"id":"msg_3","content":[{"type":"tool_use","id":"toolu_1","name":"Read","input":{}}],"usage":{"input_tokens":100,"output_tokens":50}},"requestId":"req_3","model":"claude-sonnet-4"}"#;
+        let mut entries: Vec<UsageEntry> = [text, read, read]
+            .iter()
+            .map(|l| parse_jsonl_line(l).unwrap().unwrap())
+            .collect();
+        assert_eq!(deduplicate_entries(&mut entries), 2);
+        assert_eq!(entries[0].tools.len(), 1);
+        assert_eq!(entries[0].tools[0].name, "Read");
+    }
+    
+    #[test]
+    fn test_mark_unread_cache_writes() {
+        let a = UsageEntry {
+            cache_creation_tokens: 1_000,
+            session_id: Some("a".to_string()),
+            ..UsageEntry::test_at("2024-01-01T10:00:00Z")
+        };
+        let mut entries = vec![
+            a.clone(),
+            // Another conversation in between does not read a's cache
+            UsageEntry {
+                timestamp: test_time("2024-01-01T10:01:00Z"),
+                cache_creation_tokens: 500,
+                session_id: Some("b".to_string()),
+                ..a.clone()
+            },
+            UsageEntry {
+                timestamp: test_time("2024-01-01T10:02:00Z"),
+                cache_creation_tokens: 400,
+                cache_read_tokens: 600,
+                ..a.clone()
+            },
+            // Past the 5-minute lifetime
+            UsageEntry {
+                timestamp: test_time("2024-01-01T10:30:00Z"),
+                cache_creation_tokens: 0,
+                cache_read_tokens: 1_000,
+                ..a.clone()
+            },
+        ];
+        
+        mark_unread_cache_writes(&mut entries);
+        let unread: Vec<u64> = entries.iter().map(|e| e.unread_cache_write_tokens).collect();
+        assert_eq!(unread, vec![400, 500, 400, 0]);
+        
+        // Two subagents running at once each read their own writes
+        let x = UsageEntry {
+            is_sidechain: true,
+            agent_id: Some("x".to_string()),
+            ..a
+        };
+        let mut entries = vec![
+            x.clone(),
+            UsageEntry {
+                timestamp: test_time("2024-01-01T10:01:00Z"),
+                cache_creation_tokens: 800,
+                agent_id: Some("y".to_string()),
+                ..x.clone()
+            },
+            UsageEntry {
+                timestamp: test_time("2024-01-01T10:02:00Z"),
+                cache_creation_tokens: 0,
+                cache_read_tokens: 1_000,
+                ..x
+            },
+        ];
+        mark_unread_cache_writes(&mut entries);
+        let unread: Vec<u64> = entries.iter().map(|e| e.unread_cache_write_tokens).collect();
+        assert_eq!(unread, vec![0, 800, 0]);
+    }
+    
+    #[test]
+    fn test_scan_jsonl_file_incremental() {
+        let line = |n: u64| format!(
+            r#"{{"timestamp":"2024-01-01T10:00:0{}Z","message":{{"usage":{{"input_tokens":{},"output_tokens":1}}}},"model":"claude-3-5-sonnet-20241022"}}"#,
+            n, n
+        );
+        let path = std::env::temp_dir()
+            .join(format!("claude-block-checker-scan-{}.jsonl", std::process::id()));
+        
+        // A trailing partial line is left for the next scan
+        fs::write(&path, format!("{}\n{{\"timestamp\"", line(1))).unwrap();
+        let (file, _) = scan_jsonl_file(&path, None).unwrap();
+        assert_eq!(file.entries.len(), 1);
+        assert_eq!(file.offset, line(1).len() as u64 + 1);
+        
+        // Appended lines are parsed from the saved offset
+        fs::write(&path, format!("{}\n{}\n{}\n", line(1), line(2), line(3))).unwrap();
+        let (file, updated) = scan_jsonl_file(&path, Some(file)).unwrap();
+        assert!(updated);
+        assert_eq!(file.entries.iter().map(|e| e.input_tokens).collect::<Vec<_>>(), vec![1, 2, 3]);
+        
+        // A rewritten, shorter file is parsed from scratch
+        fs::write(&path, format!("{}\n", line(4))).unwrap();
+        let (file, _) = scan_jsonl_file(&path, Some(file)).unwrap();
+        assert_eq!(file.entries.iter().map(|e| e.input_tokens).collect::<Vec<_>>(), vec![4]);
+        
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_parse_limit_event() {
+        let line = r#"{"type":"assistant","timestamp":"2024-01-01T10:00:00Z","isApiErrorMessage":true,"message":{"model":"<synthetic>","content":[{"type":"text","text":"Claude AI usage limit reached|1704117600"}],"usage":{"input_tokens":0,"output_tokens":0}}}"#;
+        
+        // Synthetic messages are not usage
+        assert!(parse_jsonl_line(line).unwrap().is_none());
+        
+        let event = parse_limit_event(line).unwrap();
+        assert_eq!(event.reset_time, Some(Utc.with_ymd_and_hms(2024, 1, 1, 14, 0, 0).unwrap()));
+        
+        // Ordinary conversation text mentioning limits is ignored
+        let chat = r#"{"type":"assistant","timestamp":"2024-01-01T10:00:00Z","message":{"model":"claude-sonnet-4-20250514","content":[{"type":"text","text":"The usage limit reached|1704117600 message"}],"usage":{"input_tokens":1,"output_tokens":1}}}"#;
+        assert!(parse_limit_event(chat).is_none());
+    }
+    
+    #[test]
+    fn test_parse_reset_time() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap();
+        
+        assert_eq!(
+            parse_reset_time("5-hour limit reached ∙ resets 2pm (UTC)", at),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 14, 0, 0).unwrap())
+        );
+        // Tokyo is UTC+9, so 3:30am there is 18:30 UTC the same day
+        assert_eq!(
+            parse_reset_time("Claude usage limit reached. Your limit will reset at 3:30am (Asia/Tokyo).", at),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 18, 30, 0).unwrap())
+        );
+        // A time already passed today means tomorrow
+        assert_eq!(
+            parse_reset_time("limit reached, resets 09:00 (UTC)", at),
+            Some(Utc.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap())
+        );
+        assert_eq!(parse_reset_time("usage limit reached", at), None);
+    }
+}
@@ -0,0 +1,841 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::ValueEnum;
+use colored::*;
+use num_format::{Locale, ToFormattedString};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::config_dir;
+use crate::models::{ModelPricing, PricingTier, TokenRates, UsageEntry};
+
+/// Prices shipped with the binary
+const BUNDLED_PRICING: &str = include_str!("pricing.toml");
+
+/// Price of the models whose ID contains `model`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PriceRule {
+    pub model: String,
+    /// First UTC day the price applies; always when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_from: Option<NaiveDate>,
+    #[serde(rename = "prices")]
+    pub pricing: ModelPricing,
+}
+
+/// How unmatched models are priced
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Fallback {
+    /// Rule name used when no family keyword appears in the model ID
+    default: Option<String>,
+    /// Family keyword ("opus") to the rule name it is priced like
+    #[serde(default)]
+    families: BTreeMap<String, String>,
+}
+
+/// Layout of the bundled table and of user pricing files
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PricingFile {
+    #[serde(default)]
+    models: Vec<PriceRule>,
+    #[serde(default)]
+    fallback: Fallback,
+}
+
+/// Where an entry's cost comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CostMode {
+    /// The cost Claude Code logged (`costUSD`), calculated when missing
+    #[default]
+    Auto,
+    /// Always calculate from the pricing table
+    Calculate,
+    /// Only the logged cost; entries without one cost nothing
+    Display,
+}
+
+impl CostMode {
+    /// Whether the entry's cost is calculated from the pricing table
+    pub fn calculates(self, entry: &UsageEntry) -> bool {
+        match self {
+            CostMode::Auto => entry.logged_cost.is_none(),
+            CostMode::Calculate => true,
+            CostMode::Display => false,
+        }
+    }
+}
+
+/// Why a model got the price it did
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase", tag = "kind", content = "family")]
+pub enum MatchKind {
+    /// A rule names the model
+    Model,
+    /// Priced like its family, e.g. an unreleased opus model
+    Family(String),
+    /// Nothing matched, so the default rule applies
+    Default,
+}
+
+/// The rule chosen for a model at a point in time
+#[derive(Debug, Clone)]
+pub struct PriceMatch<'a> {
+    pub rule: &'a PriceRule,
+    pub kind: MatchKind,
+}
+
+/// Date-aware price lookup built from the bundled table and a user file
+#[derive(Debug)]
+pub struct PricingTable {
+    rules: Vec<PriceRule>,
+    fallback: Fallback,
+    /// Used when even the fallback rule is missing
+    default_pricing: ModelPricing,
+}
+
+impl PricingTable {
+    /// The bundled table only
+    pub fn bundled() -> Self {
+        let file: PricingFile = toml::from_str(BUNDLED_PRICING).expect("bundled pricing table is valid");
+        Self {
+            rules: file.models,
+            fallback: file.fallback,
+            default_pricing: ModelPricing::default(),
+        }
+    }
+    
+    /// The bundled table overlaid with a user pricing file: `path` when
+    /// given, else `pricing.toml` or `pricing.json` in the config directory
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut table = Self::bundled();
+        let path = match path {
+            Some(path) => {
+                if !path.exists() {
+                    bail!("Pricing file {} not found", path.display());
+                }
+                Some(path.to_path_buf())
+            }
+            None => default_pricing_file(),
+        };
+        
+        if let Some(path) = path {
+            table.merge(read_pricing_file(&path)?);
+        }
+        Ok(table)
+    }
+    
+    /// Add a user file's rules after the bundled ones. Lookups prefer later
+    /// rules on ties, so a user rule with the same name and date wins.
+    fn merge(&mut self, file: PricingFile) {
+        self.rules.extend(file.models);
+        if file.fallback.default.is_some() {
+            self.fallback.default = file.fallback.default;
+        }
+        self.fallback.families.extend(file.fallback.families);
+    }
+    
+    /// Find the rule pricing `model` at `timestamp`
+    pub fn lookup(&self, model: &str, timestamp: DateTime<Utc>) -> Option<PriceMatch<'_>> {
+        let date = timestamp.date_naive();
+        
+        let by_model = self
+            .rules
+            .iter()
+            .filter(|rule| rule.effective_from.is_none_or(|from| from <= date))
+            .filter(|rule| contains_model(model, &rule.model))
+            // On ties `max_by_key` keeps the last rule, which is the user's
+            .max_by_key(|rule| (rule.model.len(), rule.effective_from));
+        if let Some(rule) = by_model {
+            return Some(PriceMatch { rule, kind: MatchKind::Model });
+        }
+        
+        let lowercase = model.to_ascii_lowercase();
+        let family = self
+            .fallback
+            .families
+            .iter()
+            .filter_map(|(family, target)| lowercase.find(family.as_str()).map(|at| (at, family, target)))
+            .min_by_key(|(at, _, _)| *at);
+        let (kind, target) = match family {
+            Some((_, family, target)) => (MatchKind::Family(family.clone()), target),
+            None => (MatchKind::Default, self.fallback.default.as_ref()?),
+        };
+        
+        self.rules
+            .iter()
+            .filter(|rule| &rule.model == target)
+            .filter(|rule| rule.effective_from.is_none_or(|from| from <= date))
+            .max_by_key(|rule| rule.effective_from)
+            .map(|rule| PriceMatch { rule, kind })
+    }
+    
+    /// Price of an entry at its own timestamp
+    pub fn pricing_for(&self, entry: &UsageEntry) -> &ModelPricing {
+        self.lookup(&entry.model, entry.timestamp)
+            .map_or(&self.default_pricing, |found| &found.rule.pricing)
+    }
+    
+    /// Set each entry's cost and pricing tier, taking the cost from the
+    /// log or the table as `mode` says
+    pub fn apply(&self, entries: &mut [UsageEntry], mode: CostMode) {
+        for entry in entries {
+            let pricing = self.pricing_for(entry);
+            entry.premium_tier = pricing.tier_for(entry.prompt_tokens()).is_some();
+            (entry.prompt_cost, entry.uncached_prompt_cost) = entry.prompt_costs(pricing);
+            entry.cost = if mode.calculates(entry) {
+                entry.calculate_cost(pricing)
+            } else {
+                entry.logged_cost.unwrap_or(0.0)
+            };
+        }
+    }
+}
+
+/// Whether `model` contains `name` as a whole dash-separated part, so
+/// "claude-opus-4" matches "claude-opus-4-1" but not "claude-opus-45"
+fn contains_model(model: &str, name: &str) -> bool {
+    model.match_indices(name).any(|(at, _)| {
+        let before = model[..at].chars().next_back();
+        let after = model[at + name.len()..].chars().next();
+        !before.is_some_and(|c| c.is_ascii_alphanumeric()) && !after.is_some_and(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+fn default_pricing_file() -> Option<PathBuf> {
+    let dir = config_dir()?;
+    ["pricing.toml", "pricing.json"]
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+}
+
+fn read_pricing_file(path: &Path) -> Result<PricingFile> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))
+    } else {
+        toml::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+}
+
+/// Entries of one model priced by one rule
+#[derive(Debug, Serialize)]
+pub struct PricingMatchRow {
+    pub model: String,
+    /// Name of the matched rule, absent when even the fallback is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_from: Option<NaiveDate>,
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<MatchKind>,
+    pub pricing: ModelPricing,
+    pub entries: usize,
+    /// Entries whose cost came from the log instead of this rule
+    pub logged_cost_entries: usize,
+    /// Entries priced at a long-context tier
+    pub premium_entries: usize,
+    pub web_search_requests: u64,
+    pub web_fetch_requests: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Group entries by model and the rule that priced them
+pub fn match_models(table: &PricingTable, entries: &[UsageEntry], mode: CostMode) -> Vec<PricingMatchRow> {
+    let mut rows: HashMap<(String, Option<String>, Option<NaiveDate>), PricingMatchRow> = HashMap::new();
+    
+    for entry in entries {
+        let found = table.lookup(&entry.model, entry.timestamp);
+        let rule = found.as_ref().map(|found| found.rule);
+        let key = (
+            entry.model.clone(),
+            rule.map(|rule| rule.model.clone()),
+            rule.and_then(|rule| rule.effective_from),
+        );
+        let row = rows.entry(key).or_insert_with(|| PricingMatchRow {
+            model: entry.model.clone(),
+            rule: rule.map(|rule| rule.model.clone()),
+            effective_from: rule.and_then(|rule| rule.effective_from),
+            kind: found.as_ref().map(|found| found.kind.clone()),
+            pricing: rule.map(|rule| rule.pricing.clone()).unwrap_or_default(),
+            entries: 0,
+            logged_cost_entries: 0,
+            premium_entries: 0,
+            web_search_requests: 0,
+            web_fetch_requests: 0,
+            first_seen: entry.timestamp,
+            last_seen: entry.timestamp,
+        });
+        row.entries += 1;
+        row.logged_cost_entries += (!mode.calculates(entry) && entry.logged_cost.is_some()) as usize;
+        row.premium_entries += entry.premium_tier as usize;
+        row.web_search_requests += entry.web_search_requests;
+        row.web_fetch_requests += entry.web_fetch_requests;
+        row.first_seen = row.first_seen.min(entry.timestamp);
+        row.last_seen = row.last_seen.max(entry.timestamp);
+    }
+    
+    let mut rows: Vec<PricingMatchRow> = rows.into_values().collect();
+    rows.sort_by(|a, b| a.model.cmp(&b.model).then(a.first_seen.cmp(&b.first_seen)));
+    rows
+}
+
+pub fn print_pricing_matches(rows: &[PricingMatchRow]) {
+    println!("{}", "Claude Code Usage - Model Pricing".bold().green());
+    println!("Prices in USD per million tokens\n");
+    
+    if rows.is_empty() {
+        println!("{}", "No usage found".yellow());
+        return;
+    }
+    
+    let width = rows.iter().map(|row| row.model.len()).chain([5]).max().unwrap_or(5);
+    let rule_width = rows
+        .iter()
+        .map(|row| describe_rule(row).len())
+        .chain(rows.iter().flat_map(|row| row.pricing.tiers.iter().map(|tier| describe_tier(tier).len())))
+        .chain([4])
+        .max()
+        .unwrap_or(4);
+    println!(
+        "{}",
+        format!(
+            "{:<width$}  {:<rule_width$} {:>8} {:>8} {:>8} {:>8} {:>8} {:>10}",
+            "Model", "Rule", "Input", "Output", "Cache+5m", "Cache+1h", "Cache-", "Entries",
+            width = width,
+            rule_width = rule_width
+        )
+        .bold()
+    );
+    
+    for row in rows {
+        let rule = describe_rule(row);
+        let rule = match row.kind {
+            Some(MatchKind::Model) => rule.normal(),
+            _ => rule.yellow(),
+        };
+        print_rates(&row.model, rule, row.pricing.rates_for(0), Some(row.entries), width, rule_width);
+        for tier in &row.pricing.tiers {
+            let rates = row.pricing.rates_for(tier.above_input_tokens + 1);
+            print_rates("", describe_tier(tier).dimmed(), rates, None, width, rule_width);
+        }
+        if row.premium_entries > 0 {
+            println!(
+                "{:<width$}  {}",
+                "",
+                format!(
+                    "{} entries were priced at a long-context tier",
+                    row.premium_entries.to_formatted_string(&Locale::en)
+                )
+                .dimmed(),
+                width = width
+            );
+        }
+        if row.web_search_requests > 0 || row.web_fetch_requests > 0 {
+            println!(
+                "{:<width$}  {}",
+                "",
+                format!(
+                    "{} web searches at ${:.2} and {} web fetches at ${:.2} per request",
+                    row.web_search_requests.to_formatted_string(&Locale::en),
+                    row.pricing.web_search_rate(),
+                    row.web_fetch_requests.to_formatted_string(&Locale::en),
+                    row.pricing.web_fetch_rate()
+                )
+                .dimmed(),
+                width = width
+            );
+        }
+        if row.logged_cost_entries > 0 {
+            println!(
+                "{:<width$}  {}",
+                "",
+                format!("{} entries used the cost logged by Claude Code", row.logged_cost_entries).dimmed(),
+                width = width
+            );
+        }
+    }
+}
+
+fn print_rates(model: &str, rule: ColoredString, rates: TokenRates, entries: Option<usize>, width: usize, rule_width: usize) {
+    println!(
+        "{:<width$}  {:<rule_width$} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>10}",
+        model,
+        rule,
+        rates.input,
+        rates.output,
+        rates.cache_write_5m,
+        rates.cache_write_1h,
+        rates.cache_read,
+        entries.map(|entries| entries.to_formatted_string(&Locale::en)).unwrap_or_default(),
+        width = width,
+        rule_width = rule_width
+    );
+}
+
+fn describe_tier(tier: &PricingTier) -> String {
+    format!("  over {} tokens", tier.above_input_tokens.to_formatted_string(&Locale::en))
+}
+
+/// A model no pricing rule names, so its price was guessed from a fallback
+#[derive(Debug, Clone, Serialize)]
+pub struct UnknownModel {
+    pub model: String,
+    /// Rule whose prices were used, absent when even the fallback is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priced_as: Option<String>,
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<MatchKind>,
+    pub entries: usize,
+    /// Entries whose cost was calculated from the guessed price
+    pub estimated_entries: usize,
+    pub total_tokens: u64,
+}
+
+/// Models among `entries` that only a fallback could price, by token volume
+pub fn unknown_models<'a>(
+    table: &PricingTable,
+    entries: impl IntoIterator<Item = &'a UsageEntry>,
+    mode: CostMode,
+) -> Vec<UnknownModel> {
+    let mut models: HashMap<&str, UnknownModel> = HashMap::new();
+    
+    for entry in entries {
+        let found = table.lookup(&entry.model, entry.timestamp);
+        if found.as_ref().is_some_and(|found| found.kind == MatchKind::Model) {
+            continue;
+        }
+        let model = models.entry(&entry.model).or_insert_with(|| UnknownModel {
+            model: entry.model.clone(),
+            priced_as: found.as_ref().map(|found| found.rule.model.clone()),
+            kind: found.map(|found| found.kind),
+            entries: 0,
+            estimated_entries: 0,
+            total_tokens: 0,
+        });
+        model.entries += 1;
+        model.estimated_entries += mode.calculates(entry) as usize;
+        model.total_tokens += entry.total_tokens;
+    }
+    
+    let mut models: Vec<UnknownModel> = models.into_values().collect();
+    models.sort_by(|a, b| b.total_tokens.cmp(&a.total_tokens).then(a.model.cmp(&b.model)));
+    models
+}
+
+/// Combine the unknown models of several profiles, by token volume
+pub fn merge_unknown_models<'a>(lists: impl IntoIterator<Item = &'a [UnknownModel]>) -> Vec<UnknownModel> {
+    let mut models: HashMap<&str, UnknownModel> = HashMap::new();
+    
+    for model in lists.into_iter().flatten() {
+        let merged = models.entry(&model.model).or_insert_with(|| UnknownModel {
+            entries: 0,
+            estimated_entries: 0,
+            total_tokens: 0,
+            ..model.clone()
+        });
+        merged.entries += model.entries;
+        merged.estimated_entries += model.estimated_entries;
+        merged.total_tokens += model.total_tokens;
+    }
+    
+    let mut models: Vec<UnknownModel> = models.into_values().collect();
+    models.sort_by(|a, b| b.total_tokens.cmp(&a.total_tokens).then(a.model.cmp(&b.model)));
+    models
+}
+
+/// Costs `--strict-pricing` refused to calculate from a guessed price
+#[derive(Debug)]
+pub struct UnknownPricing {
+    pub models: Vec<String>,
+}
+
+impl fmt::Display for UnknownPricing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "No pricing rule for {} (add one to pricing.toml or drop --strict-pricing)",
+            self.models.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownPricing {}
+
+/// Fail with [`UnknownPricing`] when any cost had to be calculated from a
+/// guessed price
+pub fn require_known_prices(unknown: &[UnknownModel]) -> Result<()> {
+    let models: Vec<String> = unknown
+        .iter()
+        .filter(|model| model.estimated_entries > 0)
+        .map(|model| model.model.clone())
+        .collect();
+    if !models.is_empty() {
+        return Err(UnknownPricing { models }.into());
+    }
+    Ok(())
+}
+
+pub fn print_unknown_models(unknown: &[UnknownModel], indent: &str) {
+    if unknown.is_empty() {
+        return;
+    }
+    
+    println!("\n{}{}", indent, "Unrecognised models, priced by a fallback:".yellow().bold());
+    for model in unknown {
+        let priced_as = match model.priced_as {
+            Some(ref rule) => format!("priced as {}", rule),
+            None => "priced with built-in defaults".to_string(),
+        };
+        println!(
+            "{}  {} - {} tokens in {} entries, {}",
+            indent,
+            model.model.yellow(),
+            model.total_tokens.to_formatted_string(&Locale::en),
+            model.entries.to_formatted_string(&Locale::en),
+            priced_as
+        );
+    }
+}
+
+/// Logged and calculated costs of one model's entries that carry `costUSD`
+#[derive(Debug, Serialize)]
+pub struct CostComparisonRow {
+    pub model: String,
+    pub entries: usize,
+    /// Entries whose costs differ by more than the tolerance
+    pub mismatched_entries: usize,
+    pub logged_cost: f64,
+    pub calculated_cost: f64,
+    /// Calculated minus logged, as a percentage of the larger
+    pub difference_percent: f64,
+    /// Largest relative difference of a single entry
+    pub max_entry_difference_percent: f64,
+}
+
+/// Compare the logged cost of every entry that has one with what the pricing
+/// table gives. Entries count as mismatched when the two differ by more than
+/// `tolerance_percent` of the larger.
+pub fn compare_costs(table: &PricingTable, entries: &[UsageEntry], tolerance_percent: f64) -> Vec<CostComparisonRow> {
+    let mut rows: BTreeMap<&str, CostComparisonRow> = BTreeMap::new();
+    
+    for entry in entries {
+        let Some(logged) = entry.logged_cost else {
+            continue;
+        };
+        let calculated = entry.calculate_cost(table.pricing_for(entry));
+        let difference = relative_difference(logged, calculated);
+        
+        let row = rows.entry(&entry.model).or_insert_with(|| CostComparisonRow {
+            model: entry.model.clone(),
+            entries: 0,
+            mismatched_entries: 0,
+            logged_cost: 0.0,
+            calculated_cost: 0.0,
+            difference_percent: 0.0,
+            max_entry_difference_percent: 0.0,
+        });
+        row.entries += 1;
+        row.mismatched_entries += (difference > tolerance_percent) as usize;
+        row.logged_cost += logged;
+        row.calculated_cost += calculated;
+        row.max_entry_difference_percent = row.max_entry_difference_percent.max(difference);
+    }
+    
+    rows.into_values()
+        .map(|mut row| {
+            let difference = relative_difference(row.logged_cost, row.calculated_cost);
+            row.difference_percent = if row.calculated_cost < row.logged_cost { -difference } else { difference };
+            row
+        })
+        .collect()
+}
+
+/// Difference between two costs as a percentage of the larger
+fn relative_difference(a: f64, b: f64) -> f64 {
+    let larger = a.abs().max(b.abs());
+    if larger == 0.0 {
+        0.0
+    } else {
+        (a - b).abs() / larger * 100.0
+    }
+}
+
+pub fn print_cost_comparison(rows: &[CostComparisonRow], tolerance_percent: f64) {
+    println!("{}", "Claude Code Usage - Logged vs Calculated Cost".bold().green());
+    println!("Tolerance: {}%\n", tolerance_percent);
+    
+    if rows.is_empty() {
+        println!("{}", "No entries with a logged cost".yellow());
+        return;
+    }
+    
+    let width = rows.iter().map(|row| row.model.len()).chain([5]).max().unwrap_or(5);
+    println!(
+        "{}",
+        format!(
+            "{:<width$} {:>10} {:>10} {:>12} {:>12} {:>9} {:>9}",
+            "Model", "Entries", "Mismatch", "Logged", "Calculated", "Diff", "Max",
+            width = width
+        )
+        .bold()
+    );
+    
+    for row in rows {
+        let line = format!(
+            "{:<width$} {:>10} {:>10} {:>12} {:>12} {:>8.2}% {:>8.2}%",
+            row.model,
+            row.entries.to_formatted_string(&Locale::en),
+            row.mismatched_entries.to_formatted_string(&Locale::en),
+            format!("${:.4}", row.logged_cost),
+            format!("${:.4}", row.calculated_cost),
+            row.difference_percent,
+            row.max_entry_difference_percent,
+            width = width
+        );
+        if row.mismatched_entries > 0 {
+            println!("{}", line.yellow());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Rule name with its start date and how it was reached
+fn describe_rule(row: &PricingMatchRow) -> String {
+    let Some(ref rule) = row.rule else {
+        return "built-in default".to_string();
+    };
+    let mut text = rule.clone();
+    if let Some(from) = row.effective_from {
+        text.push_str(&format!(" (from {})", from));
+    }
+    match row.kind {
+        Some(MatchKind::Family(ref family)) => text.push_str(&format!(" [{} family]", family)),
+        Some(MatchKind::Default) => text.push_str(" [default]"),
+        _ => {}
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_time;
+    
+    #[test]
+    fn test_lookup_prefers_longest_name_and_family() {
+        let table = PricingTable::bundled();
+        let now = test_time("2025-12-01T00:00:00Z");
+        
+        let found = table.lookup("claude-opus-4-1-20250805", now).unwrap();
+        assert_eq!(found.rule.model, "claude-opus-4-1");
+        assert_eq!(found.kind, MatchKind::Model);
+        
+        let found = table.lookup("us.anthropic.claude-haiku-4-5-20251001-v1:0", now).unwrap();
+        assert_eq!(found.rule.model, "claude-haiku-4-5");
+        
+        let found = table.lookup("claude-sonnet-4-5@20250929", now).unwrap();
+        assert_eq!(found.rule.model, "claude-sonnet-4-5");
+        
+        assert!(!contains_model("claude-opus-45", "claude-opus-4"));
+        let found = table.lookup("claude-opus-9", now).unwrap();
+        assert_eq!(found.kind, MatchKind::Family("opus".to_string()));
+        assert_eq!(found.rule.model, "claude-opus-4");
+        
+        assert_eq!(table.lookup("mystery-model", now).unwrap().kind, MatchKind::Default);
+    }
+    
+    #[test]
+    fn test_bundled_price_changes() {
+        let table = PricingTable::bundled();
+        let input_price = |model: &str, timestamp: &str| {
+            table.lookup(model, test_time(timestamp)).unwrap().rule.pricing.input_cost_per_million
+        };
+        assert_eq!(input_price("claude-opus-4-5-20251101", "2025-11-23T23:59:59Z"), 15.0);
+        assert_eq!(input_price("claude-opus-4-5-20251101", "2025-11-24T00:00:00Z"), 5.0);
+        
+        let tiers = |timestamp: &str| {
+            table.lookup("claude-sonnet-4-20250514", test_time(timestamp)).unwrap().rule.pricing.tiers.len()
+        };
+        assert_eq!(tiers("2025-08-11T12:00:00Z"), 0);
+        assert_eq!(tiers("2025-08-12T00:00:00Z"), 1);
+    }
+    
+    #[test]
+    fn test_effective_dates_and_user_overrides() {
+        let mut table = PricingTable::bundled();
+        let user: PricingFile = toml::from_str(
+            r#"
+            [[models]]
+            model = "claude-sonnet-4-5"
+            prices = { input = 3.0, output = 15.0, cache_write = 3.75, cache_read = 0.30 }
+            
+            [[models]]
+            model = "claude-sonnet-4-5"
+            effective_from = "2026-01-01"
+            prices = { input = 2.0, output = 10.0, cache_write = 2.5, cache_read = 0.20 }
+            "#,
+        )
+        .unwrap();
+        table.merge(user);
+        
+        let model = "claude-sonnet-4-5-20250929";
+        let before = table.lookup(model, test_time("2025-12-31T23:59:59Z")).unwrap();
+        assert_eq!(before.rule.pricing.input_cost_per_million, 3.0);
+        let after = table.lookup(model, test_time("2026-01-01T00:00:00Z")).unwrap();
+        assert_eq!(after.rule.pricing.input_cost_per_million, 2.0);
+        
+        let entry = UsageEntry {
+            input_tokens: 1_000_000,
+            model: model.to_string(),
+            ..UsageEntry::test_at("2026-02-01T00:00:00Z")
+        };
+        let mut entries = vec![entry.clone(), UsageEntry { logged_cost: Some(0.5), ..entry }];
+        table.apply(&mut entries, CostMode::Auto);
+        assert_eq!(entries[0].cost, 2.0);
+        assert_eq!(entries[1].cost, 0.5);
+        
+        let typo = "[[models]]\nmodel = \"x\"\nprices = { input = 1.0, output = 1.0, cache_write = 1.0, cache_read = 1.0, cache_wirte = 2.0 }";
+        assert!(toml::from_str::<PricingFile>(typo).is_err());
+    }
+    
+    #[test]
+    fn test_unknown_models() {
+        let table = PricingTable::bundled();
+        let sonnet = UsageEntry {
+            total_tokens: 100,
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            ..UsageEntry::test_at("2025-10-01T00:00:00Z")
+        };
+        let mystery = UsageEntry {
+            total_tokens: 500,
+            model: "mystery-model".to_string(),
+            ..sonnet.clone()
+        };
+        let entries = vec![
+            UsageEntry { total_tokens: 50, model: "claude-opus-9".to_string(), ..sonnet.clone() },
+            sonnet,
+            UsageEntry { logged_cost: Some(1.0), ..mystery.clone() },
+            mystery,
+        ];
+        
+        let unknown = unknown_models(&table, &entries, CostMode::Auto);
+        assert_eq!(unknown.len(), 2);
+        assert_eq!(unknown[0].model, "mystery-model");
+        assert_eq!(unknown[0].total_tokens, 1000);
+        assert_eq!(unknown[0].estimated_entries, 1);
+        assert_eq!(unknown[1].priced_as.as_deref(), Some("claude-opus-4"));
+        assert!(require_known_prices(&unknown).unwrap_err().is::<UnknownPricing>());
+        
+        // Only logged costs are shown, so nothing is guessed
+        assert!(require_known_prices(&unknown_models(&table, &entries, CostMode::Display)).is_ok());
+        
+        let merged = merge_unknown_models([&unknown[..], &unknown[..1]]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].total_tokens, 2000);
+        assert_eq!(merged[0].estimated_entries, 2);
+        assert_eq!(merged[1].entries, 1);
+    }
+    
+    #[test]
+    fn test_cost_modes_and_comparison() {
+        let table = PricingTable::bundled();
+        let opus = UsageEntry {
+            input_tokens: 1_000_000,
+            model: "claude-opus-4-1".to_string(),
+            ..UsageEntry::test_at("2025-10-01T00:00:00Z")
+        };
+        let logged = |costs: &[Option<f64>]| -> Vec<UsageEntry> {
+            costs.iter().map(|&logged_cost| UsageEntry { logged_cost, ..opus.clone() }).collect()
+        };
+        let costs = |mode| {
+            let mut entries = logged(&[Some(15.0), Some(20.0), None]);
+            table.apply(&mut entries, mode);
+            entries.iter().map(|e| e.cost).collect::<Vec<_>>()
+        };
+        assert_eq!(costs(CostMode::Auto), vec![15.0, 20.0, 15.0]);
+        assert_eq!(costs(CostMode::Calculate), vec![15.0, 15.0, 15.0]);
+        assert_eq!(costs(CostMode::Display), vec![15.0, 20.0, 0.0]);
+        
+        let entries = logged(&[Some(15.0), Some(15.1), Some(20.0), None]);
+        let rows = compare_costs(&table, &entries, 1.0);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].entries, 3);
+        assert_eq!(rows[0].mismatched_entries, 1);
+        assert!((rows[0].max_entry_difference_percent - 25.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_long_context_tier_and_cache_write_rates() {
+        let table = PricingTable::bundled();
+        let sonnet = UsageEntry {
+            input_tokens: 1_000,
+            output_tokens: 1_000_000,
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            ..UsageEntry::test_at("2025-10-01T00:00:00Z")
+        };
+        // Cache reads count towards the prompt size
+        let mut entries = vec![
+            UsageEntry { cache_read_tokens: 199_000, ..sonnet.clone() },
+            UsageEntry { cache_read_tokens: 199_001, ..sonnet.clone() },
+        ];
+        table.apply(&mut entries, CostMode::Auto);
+        
+        assert!(!entries[0].premium_tier);
+        assert!((entries[0].cost - (0.003 + 0.0597 + 15.0)).abs() < 1e-9);
+        assert!(entries[1].premium_tier);
+        assert!((entries[1].cost - (0.006 + 0.1194006 + 22.5)).abs() < 1e-9);
+        
+        // 1-hour cache writes cost twice the input rate
+        let mut cached = vec![UsageEntry {
+            cache_creation_tokens: 3_000_000,
+            cache_creation_5m_tokens: 2_000_000,
+            cache_creation_1h_tokens: 1_000_000,
+            model: "claude-opus-4-1".to_string(),
+            ..UsageEntry::test_at("2025-10-01T00:00:00Z")
+        }];
+        table.apply(&mut cached, CostMode::Auto);
+        assert!((cached[0].cost - (2.0 * 18.75 + 30.0)).abs() < 1e-9);
+        // Writes cost more than the same prompt sent uncached
+        assert!((cached[0].prompt_cost - cached[0].cost).abs() < 1e-9);
+        assert!((cached[0].uncached_prompt_cost - 45.0).abs() < 1e-9);
+        
+        // Opus 4.1 has no long-context tier
+        let mut opus = vec![UsageEntry {
+            input_tokens: 500_000,
+            model: "claude-opus-4-1".to_string(),
+            ..sonnet
+        }];
+        table.apply(&mut opus, CostMode::Auto);
+        assert!(!opus[0].premium_tier);
+    }
+    
+    #[test]
+    fn test_server_tool_requests_priced() {
+        let entry = UsageEntry {
+            web_search_requests: 3,
+            web_fetch_requests: 2,
+            model: "claude-sonnet-4-5".to_string(),
+            ..UsageEntry::test_at("2025-10-01T00:00:00Z")
+        };
+        let mut entries = vec![entry.clone()];
+        PricingTable::bundled().apply(&mut entries, CostMode::Auto);
+        assert!((entries[0].cost - 0.03).abs() < 1e-9);
+        
+        let pricing: ModelPricing = toml::from_str(
+            "input = 3.0\noutput = 15.0\ncache_write = 3.75\ncache_read = 0.30\nweb_search = 0.02\nweb_fetch = 0.001",
+        )
+        .unwrap();
+        assert!((entry.calculate_cost(&pricing) - 0.062).abs() < 1e-9);
+    }
+}
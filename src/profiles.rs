@@ -0,0 +1,179 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// Environment variable overriding the profiles directory
+pub const PROFILES_DIR_ENV: &str = "CLAUDE_PROFILES_DIR";
+
+/// Profiles directory used when none is given
+const DEFAULT_PROFILES_DIR: &str = "claude-profiles";
+
+/// A profile name and its Claude config directory (the one with `projects/`)
+pub type Profile = (String, PathBuf);
+
+/// Find every profile, sorted by name. Profiles listed in the config file
+/// come first, then each directory under the profiles directory, then the
+/// standard Claude config directories. When two share a name or a path, the
+/// earlier one wins.
+pub fn discover_profiles(config: &Config) -> Result<Vec<Profile>> {
+    let home = home::home_dir();
+    let mut candidates = Vec::new();
+    
+    for (name, path) in &config.profiles {
+        let path = expand_home(path, home.as_deref());
+        if path.is_dir() {
+            candidates.push((name.clone(), path));
+        } else {
+            eprintln!("{}", format!("Profile {}: {} not found", name, path.display()).yellow());
+        }
+    }
+    
+    match config.profiles_dir.as_deref() {
+        Some(dir) => {
+            let dir = expand_home(dir, home.as_deref());
+            if !dir.is_dir() {
+                bail!("Profiles directory {} not found", dir.display());
+            }
+            candidates.extend(profiles_in(&dir)?);
+        }
+        None => {
+            if let Some(dir) = home.as_ref().map(|home| home.join(DEFAULT_PROFILES_DIR)) {
+                if dir.is_dir() {
+                    candidates.extend(profiles_in(&dir)?);
+                }
+            }
+        }
+    }
+    
+    candidates.extend(implicit_profiles(home.as_deref()));
+    
+    let mut profiles = dedup_profiles(candidates);
+    profiles.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(profiles)
+}
+
+/// Each non-hidden directory under a profiles directory
+fn profiles_in(profiles_dir: &Path) -> Result<Vec<Profile>> {
+    let mut profiles = Vec::new();
+    let entries = fs::read_dir(profiles_dir)
+        .with_context(|| format!("Failed to read {}", profiles_dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if !name.starts_with('.') {
+                    profiles.push((name.to_string(), path));
+                }
+            }
+        }
+    }
+    Ok(profiles)
+}
+
+/// `CLAUDE_CONFIG_DIR`, `~/.claude` and `~/.config/claude`, for setups
+/// without a profiles directory. The standard directories only count once
+/// Claude Code has written logs to them.
+fn implicit_profiles(home: Option<&Path>) -> Vec<Profile> {
+    let mut profiles = Vec::new();
+    
+    if let Some(dir) = std::env::var_os("CLAUDE_CONFIG_DIR").filter(|dir| !dir.is_empty()) {
+        let path = PathBuf::from(dir);
+        if path.is_dir() {
+            profiles.push((config_dir_name(&path), path));
+        }
+    }
+    
+    if let Some(home) = home {
+        for (name, path) in [("default", home.join(".claude")), ("default-xdg", home.join(".config/claude"))] {
+            if path.join("projects").is_dir() {
+                profiles.push((name.to_string(), path));
+            }
+        }
+    }
+    
+    profiles
+}
+
+/// Profile name for a `CLAUDE_CONFIG_DIR`, e.g. `~/.claude-work` -> `claude-work`
+fn config_dir_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.trim_start_matches('.'))
+        .filter(|name| !name.is_empty())
+        .unwrap_or("claude-config")
+        .to_string()
+}
+
+/// Drop profiles whose name or directory was already taken
+fn dedup_profiles(candidates: Vec<Profile>) -> Vec<Profile> {
+    let mut names = HashSet::new();
+    let mut paths = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|(name, path)| {
+            let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            !paths.contains(&canonical) && names.insert(name.clone()) && paths.insert(canonical)
+        })
+        .collect()
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_home(path: &Path, home: Option<&Path>) -> PathBuf {
+    match (path.strip_prefix("~"), home) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The named profile, or every profile when no name is given
+pub fn select_profiles(profiles: &[Profile], profile_name: Option<&str>) -> Result<Vec<Profile>> {
+    match profile_name {
+        Some(name) => match profiles.iter().find(|(profile, _)| profile == name) {
+            Some(profile) => Ok(vec![profile.clone()]),
+            None => bail!("Profile '{}' not found", name),
+        },
+        None => Ok(profiles.to_vec()),
+    }
+}
+
+/// The profile whose directory contains `path`, preferring the deepest
+/// match when profile directories are nested
+pub fn profile_containing<'a>(profiles: &'a [Profile], path: &Path) -> Option<&'a Profile> {
+    profiles
+        .iter()
+        .filter(|(_, dir)| path.starts_with(dir))
+        .max_by_key(|(_, dir)| dir.components().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_dedup_and_lookup() {
+        let home = Path::new("/home/me");
+        assert_eq!(expand_home(Path::new("~/work/.claude"), Some(home)), PathBuf::from("/home/me/work/.claude"));
+        assert_eq!(config_dir_name(Path::new("/home/me/.claude-work")), "claude-work");
+        
+        let profiles = dedup_profiles(vec![
+            ("work".to_string(), PathBuf::from("/home/me/claude-profiles/work")),
+            ("work".to_string(), PathBuf::from("/home/me/.claude-work")),
+            ("other".to_string(), PathBuf::from("/home/me/claude-profiles/work")),
+            ("default".to_string(), PathBuf::from("/home/me/.claude")),
+        ]);
+        assert_eq!(profiles.len(), 2);
+        
+        let transcript = Path::new("/home/me/claude-profiles/work/projects/-home-me-repo/abc.jsonl");
+        assert_eq!(profile_containing(&profiles, transcript).unwrap().0, "work");
+        assert!(profile_containing(&profiles, Path::new("/home/me/.config/claude/x.jsonl")).is_none());
+        
+        assert_eq!(select_profiles(&profiles, Some("work")).unwrap().len(), 1);
+        assert_eq!(select_profiles(&profiles, None).unwrap().len(), 2);
+        assert!(select_profiles(&profiles, Some("missing")).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use clap::ValueEnum;
+use colored::*;
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::models::UsageEntry;
+use crate::report::UsageSummary;
+
+/// How the projects report is ranked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProjectSort {
+    Tokens,
+    Cost,
+}
+
+/// Usage of one project directory
+#[derive(Debug, Serialize)]
+pub struct ProjectUsage {
+    /// Working directory of the project
+    pub project: String,
+    pub profiles: BTreeSet<String>,
+    #[serde(flatten)]
+    pub usage: UsageSummary,
+}
+
+/// Projects ranked by usage within a time window
+#[derive(Debug, Serialize)]
+pub struct ProjectsReport {
+    /// The window covered, e.g. "active blocks"
+    pub scope: String,
+    pub projects: Vec<ProjectUsage>,
+    pub totals: UsageSummary,
+}
+
+/// Group entries by project and rank them. Entries of one project
+/// directory stay together even when only some of them logged a `cwd`.
+pub fn build_projects_report<'a>(
+    entries: impl IntoIterator<Item = (&'a str, &'a UsageEntry)>,
+    sort: ProjectSort,
+    scope: String,
+) -> ProjectsReport {
+    let mut projects: HashMap<&str, ProjectUsage> = HashMap::new();
+    let mut totals = UsageSummary::default();
+    
+    for (profile, entry) in entries {
+        let project = projects.entry(project_key(entry)).or_insert_with(|| ProjectUsage {
+            project: entry.project_path().to_string(),
+            profiles: BTreeSet::new(),
+            usage: UsageSummary::default(),
+        });
+        prefer_exact_path(&mut project.project, entry);
+        project.profiles.insert(profile.to_string());
+        project.usage.add(entry);
+        totals.add(entry);
+    }
+    
+    let mut projects: Vec<ProjectUsage> = projects.into_values().collect();
+    projects.sort_by(|a, b| {
+        let by_tokens = b.usage.total_tokens.cmp(&a.usage.total_tokens);
+        let by_cost = b.usage.total_cost.total_cmp(&a.usage.total_cost);
+        match sort {
+            ProjectSort::Tokens => by_tokens.then(by_cost),
+            ProjectSort::Cost => by_cost.then(by_tokens),
+        }
+        .then(a.project.cmp(&b.project))
+    });
+    
+    ProjectsReport { scope, projects, totals }
+}
+
+/// Key grouping the entries of one project directory, whether or not they
+/// logged a `cwd`
+pub fn project_key(entry: &UsageEntry) -> &str {
+    entry.project.as_deref().or(entry.cwd.as_deref()).unwrap_or("")
+}
+
+/// Replace a project's label with the entry's exact working directory,
+/// which beats the lossy decoded one
+pub fn prefer_exact_path(label: &mut String, entry: &UsageEntry) {
+    if entry.cwd.as_deref() == Some(entry.project_path()) && label != entry.project_path() {
+        *label = entry.project_path().to_string();
+    }
+}
+
+/// Width of the rank column and the columns after the project name,
+/// counting the spaces between them and before the profiles list
+const PROJECT_COLUMNS_WIDTH: usize = 3 + 9 + 15 + 7 + 11 + 8;
+
+pub fn print_projects_report(report: &ProjectsReport) {
+    println!("{}", "Claude Code Usage - Projects".bold().green());
+    println!("Scope: {}\n", report.scope);
+    
+    if report.projects.is_empty() {
+        println!("{}", "No usage in the selected range".yellow());
+        return;
+    }
+    
+    let width = report
+        .projects
+        .iter()
+        .map(|project| project.project.len())
+        .chain(["Project".len()])
+        .max()
+        .unwrap_or(7);
+    println!(
+        "{}",
+        format!(
+            "{:>3}  {:<width$} {:>9} {:>15} {:>7} {:>11}  Profiles",
+            "#", "Project", "Requests", "Tokens", "Share", "Cost",
+            width = width
+        )
+        .bold()
+    );
+    
+    for (rank, project) in report.projects.iter().enumerate() {
+        let share = if report.totals.total_tokens > 0 {
+            project.usage.total_tokens as f64 / report.totals.total_tokens as f64 * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "{:>3}  {:<width$} {:>9} {:>15} {:>6.1}% {:>11}  {}",
+            rank + 1,
+            project.project,
+            project.usage.entry_count.to_formatted_string(&Locale::en),
+            project.usage.total_tokens.to_formatted_string(&Locale::en),
+            share,
+            format!("${:.2}", project.usage.total_cost),
+            project.profiles.iter().cloned().collect::<Vec<_>>().join(", "),
+            width = width
+        );
+    }
+    
+    println!("{}", "─".repeat(width + PROJECT_COLUMNS_WIDTH));
+    println!(
+        "{:>3}  {:<width$} {:>9} {:>15} {:>7} {:>11}",
+        "",
+        "Total",
+        report.totals.entry_count.to_formatted_string(&Locale::en),
+        report.totals.total_tokens.to_formatted_string(&Locale::en),
+        "",
+        format!("${:.2}", report.totals.total_cost),
+        width = width
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_projects_grouped_and_ranked() {
+        let repo = UsageEntry {
+            project: Some("/home/me/my/repo".to_string()),
+            total_tokens: 100,
+            cost: 1.0,
+            ..UsageEntry::test_at("2024-01-01T10:00:00Z")
+        };
+        let entries = [
+            ("work", repo.clone()),
+            ("work", UsageEntry { cwd: Some("/home/me/my.repo".to_string()), ..repo.clone() }),
+            ("home", UsageEntry {
+                project: Some("/home/me/other".to_string()),
+                cwd: Some("/home/me/other".to_string()),
+                total_tokens: 150,
+                cost: 3.0,
+                ..repo
+            }),
+        ];
+        let iter = || entries.iter().map(|(profile, entry)| (*profile, entry));
+        
+        let report = build_projects_report(iter(), ProjectSort::Tokens, "test".to_string());
+        assert_eq!(report.projects.len(), 2);
+        assert_eq!(report.projects[0].project, "/home/me/my.repo");
+        assert_eq!(report.projects[0].usage.total_tokens, 200);
+        assert_eq!(report.totals.total_tokens, 350);
+        
+        let report = build_projects_report(iter(), ProjectSort::Cost, "test".to_string());
+        assert_eq!(report.projects[0].project, "/home/me/other");
+    }
+}
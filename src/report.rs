@@ -0,0 +1,439 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use colored::*;
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::models::{CacheEfficiency, ThreadSplit, UsageEntry};
+use crate::pricing::{print_unknown_models, UnknownModel};
+
+/// Calendar period a report groups entries by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    Daily,
+    /// Weeks start on Monday
+    Weekly,
+    Monthly,
+}
+
+/// Timezone deciding which calendar day an entry belongs to
+#[derive(Debug, Clone, Copy)]
+pub enum ReportZone {
+    Local,
+    Named(Tz),
+}
+
+impl ReportZone {
+    pub fn new(timezone: Option<Tz>) -> Self {
+        timezone.map_or(ReportZone::Local, ReportZone::Named)
+    }
+    
+    /// Calendar date of a timestamp in this zone
+    pub fn date(&self, timestamp: DateTime<Utc>) -> NaiveDate {
+        match self {
+            ReportZone::Local => timestamp.with_timezone(&Local).date_naive(),
+            ReportZone::Named(tz) => timestamp.with_timezone(tz).date_naive(),
+        }
+    }
+    
+    /// Format a timestamp as wall-clock time in this zone
+    pub fn format(&self, timestamp: DateTime<Utc>, format: &str) -> String {
+        match self {
+            ReportZone::Local => timestamp.with_timezone(&Local).format(format).to_string(),
+            ReportZone::Named(tz) => timestamp.with_timezone(tz).format(format).to_string(),
+        }
+    }
+    
+    pub fn name(&self) -> String {
+        match self {
+            ReportZone::Local => "local".to_string(),
+            ReportZone::Named(tz) => tz.name().to_string(),
+        }
+    }
+}
+
+/// Inclusive date range, evaluated in a report timezone
+#[derive(Debug, Clone, Copy)]
+pub struct DateFilter {
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub zone: ReportZone,
+}
+
+impl DateFilter {
+    pub fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        let date = self.zone.date(timestamp);
+        self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date <= until)
+    }
+}
+
+/// Token and cost totals for a group of entries
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageSummary {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_creation_5m_tokens: u64,
+    pub cache_creation_1h_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub total_tokens: u64,
+    pub web_search_requests: u64,
+    pub web_fetch_requests: u64,
+    pub total_cost: f64,
+    pub models: BTreeSet<String>,
+    pub entry_count: usize,
+    /// Requests priced at a long-context tier
+    pub premium_requests: usize,
+    pub threads: ThreadSplit,
+    pub cache: CacheEfficiency,
+}
+
+impl UsageSummary {
+    pub fn add(&mut self, entry: &UsageEntry) {
+        self.input_tokens += entry.input_tokens;
+        self.output_tokens += entry.output_tokens;
+        self.cache_creation_tokens += entry.cache_creation_tokens;
+        self.cache_creation_5m_tokens += entry.cache_creation_5m_tokens;
+        self.cache_creation_1h_tokens += entry.cache_creation_1h_tokens;
+        self.cache_read_tokens += entry.cache_read_tokens;
+        self.total_tokens += entry.total_tokens;
+        self.web_search_requests += entry.web_search_requests;
+        self.web_fetch_requests += entry.web_fetch_requests;
+        self.total_cost += entry.cost;
+        self.models.insert(entry.model.clone());
+        self.entry_count += 1;
+        self.premium_requests += entry.premium_tier as usize;
+        self.threads.add(entry);
+        self.cache.add(entry);
+    }
+    
+    pub fn merge(&mut self, other: &UsageSummary) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_creation_5m_tokens += other.cache_creation_5m_tokens;
+        self.cache_creation_1h_tokens += other.cache_creation_1h_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.total_tokens += other.total_tokens;
+        self.web_search_requests += other.web_search_requests;
+        self.web_fetch_requests += other.web_fetch_requests;
+        self.total_cost += other.total_cost;
+        self.models.extend(other.models.iter().cloned());
+        self.entry_count += other.entry_count;
+        self.premium_requests += other.premium_requests;
+        self.threads.merge(&other.threads);
+        self.cache.merge(&other.cache);
+    }
+}
+
+/// Usage of one profile within a report row
+#[derive(Debug, Serialize)]
+pub struct ProfileSummary {
+    pub profile: String,
+    #[serde(flatten)]
+    pub usage: UsageSummary,
+}
+
+/// One calendar period of a report
+#[derive(Debug, Serialize)]
+pub struct ReportRow {
+    /// First day of the period
+    pub start_date: NaiveDate,
+    pub label: String,
+    #[serde(flatten)]
+    pub usage: UsageSummary,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<ProfileSummary>,
+}
+
+/// Usage grouped by calendar period
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub period: ReportPeriod,
+    pub timezone: String,
+    pub rows: Vec<ReportRow>,
+    pub totals: UsageSummary,
+    /// Models priced by a fallback rule, set by the caller
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_models: Vec<UnknownModel>,
+}
+
+/// First day of the period containing `date`
+fn period_start(period: ReportPeriod, date: NaiveDate) -> NaiveDate {
+    match period {
+        ReportPeriod::Daily => date,
+        ReportPeriod::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        ReportPeriod::Monthly => date.with_day(1).unwrap(),
+    }
+}
+
+fn period_label(period: ReportPeriod, start: NaiveDate) -> String {
+    match period {
+        ReportPeriod::Daily => start.format("%Y-%m-%d").to_string(),
+        ReportPeriod::Weekly => start.format("%G-W%V").to_string(),
+        ReportPeriod::Monthly => start.format("%Y-%m").to_string(),
+    }
+}
+
+/// Group each profile's entries by calendar period. Profile breakdowns are
+/// only kept when `breakdown` is set.
+pub fn build_report(
+    profiles: &[(String, Vec<UsageEntry>)],
+    period: ReportPeriod,
+    filter: &DateFilter,
+    breakdown: bool,
+) -> UsageReport {
+    let mut periods: BTreeMap<NaiveDate, BTreeMap<&str, UsageSummary>> = BTreeMap::new();
+    
+    for (profile, entries) in profiles {
+        for entry in entries.iter().filter(|entry| filter.contains(entry.timestamp)) {
+            let start = period_start(period, filter.zone.date(entry.timestamp));
+            periods
+                .entry(start)
+                .or_default()
+                .entry(profile.as_str())
+                .or_default()
+                .add(entry);
+        }
+    }
+    
+    let mut totals = UsageSummary::default();
+    let rows = periods
+        .into_iter()
+        .map(|(start_date, by_profile)| {
+            let mut usage = UsageSummary::default();
+            for summary in by_profile.values() {
+                usage.merge(summary);
+            }
+            totals.merge(&usage);
+            
+            let profiles = if breakdown {
+                by_profile
+                    .into_iter()
+                    .map(|(profile, usage)| ProfileSummary {
+                        profile: profile.to_string(),
+                        usage,
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            
+            ReportRow {
+                start_date,
+                label: period_label(period, start_date),
+                usage,
+                profiles,
+            }
+        })
+        .collect();
+    
+    UsageReport {
+        period,
+        timezone: filter.zone.name(),
+        rows,
+        totals,
+        unknown_models: Vec::new(),
+    }
+}
+
+/// Width of the summary columns after the first one, counting the spaces
+/// before each and before the models list
+const SUMMARY_COLUMNS_WIDTH: usize = 12 + 12 + 14 + 14 + 15 + 11 + 9 + 6 + 10;
+
+/// Print the column headers shared by the usage tables
+pub fn print_summary_header(first_column: &str, width: usize) {
+    println!(
+        "{}",
+        format!(
+            "{:<width$} {:>12} {:>12} {:>14} {:>14} {:>15} {:>11} {:>9} {:>6}  Models",
+            first_column, "Input", "Output", "Cache+", "Cache-", "Total", "Cost", "Subagent", "Hit",
+            width = width
+        )
+        .bold()
+    );
+}
+
+/// Print one table row of token and cost totals
+pub fn print_summary_row(label: &str, width: usize, usage: &UsageSummary) {
+    println!(
+        "{:<width$} {:>12} {:>12} {:>14} {:>14} {:>15} {:>11} {:>9} {:>6}  {}",
+        label,
+        usage.input_tokens.to_formatted_string(&Locale::en),
+        usage.output_tokens.to_formatted_string(&Locale::en),
+        usage.cache_creation_tokens.to_formatted_string(&Locale::en),
+        usage.cache_read_tokens.to_formatted_string(&Locale::en),
+        usage.total_tokens.to_formatted_string(&Locale::en),
+        format!("${:.2}", usage.total_cost),
+        usage.threads.subagent_label(),
+        usage.cache.hit_label(),
+        usage.models.iter().cloned().collect::<Vec<_>>().join(", "),
+        width = width
+    );
+}
+
+/// Print the caching metrics of a block, session or report, one per line
+pub fn print_cache_efficiency(cache: &CacheEfficiency, indent: &str) {
+    println!(
+        "{}Hit ratio:   {:.1}% of {} prompt tokens",
+        indent,
+        cache.hit_ratio,
+        cache.prompt_tokens.to_formatted_string(&Locale::en)
+    );
+    println!(
+        "{}Prompt cost: ${:.2}/M tokens (${:.2}/M without caching)",
+        indent, cache.cost_per_million, cache.uncached_cost_per_million
+    );
+    let savings = format!("${:.4}", cache.savings);
+    println!(
+        "{}Saved:       {}",
+        indent,
+        if cache.savings < 0.0 { savings.red() } else { savings.green() }
+    );
+    if cache.cache_write_tokens > 0 {
+        println!(
+            "{}Never read:  {} of {} cache write tokens ({:.1}%)",
+            indent,
+            cache.unread_write_tokens.to_formatted_string(&Locale::en),
+            cache.cache_write_tokens.to_formatted_string(&Locale::en),
+            cache.unread_write_tokens as f64 / cache.cache_write_tokens as f64 * 100.0
+        );
+    }
+}
+
+pub fn print_report(report: &UsageReport) {
+    let title = match report.period {
+        ReportPeriod::Daily => "Daily",
+        ReportPeriod::Weekly => "Weekly",
+        ReportPeriod::Monthly => "Monthly",
+    };
+    println!("{}", format!("Claude Code Usage - {} Report", title).bold().green());
+    println!("Timezone: {}\n", report.timezone);
+    
+    if report.rows.is_empty() {
+        println!("{}", "No usage in the selected range".yellow());
+        return;
+    }
+    
+    let width = report
+        .rows
+        .iter()
+        .flat_map(|row| row.profiles.iter().map(|p| p.profile.len() + 2))
+        .chain([10, "Total".len()])
+        .max()
+        .unwrap_or(10);
+    
+    print_summary_header("Period", width);
+    for row in &report.rows {
+        print_summary_row(&row.label, width, &row.usage);
+        for profile in &row.profiles {
+            print_summary_row(&format!("  {}", profile.profile), width, &profile.usage);
+        }
+    }
+    
+    println!("{}", "─".repeat(width + SUMMARY_COLUMNS_WIDTH));
+    print_summary_row("Total", width, &report.totals);
+    
+    if report.totals.premium_requests > 0 {
+        println!(
+            "\n{}",
+            format!(
+                "{} of {} requests were priced at the long-context tier",
+                report.totals.premium_requests.to_formatted_string(&Locale::en),
+                report.totals.entry_count.to_formatted_string(&Locale::en)
+            )
+            .yellow()
+        );
+    }
+    
+    if report.totals.web_search_requests > 0 || report.totals.web_fetch_requests > 0 {
+        println!(
+            "\nServer tools: {} web searches, {} web fetches",
+            report.totals.web_search_requests.to_formatted_string(&Locale::en),
+            report.totals.web_fetch_requests.to_formatted_string(&Locale::en)
+        );
+    }
+    
+    if report.totals.cache.prompt_tokens > 0 {
+        println!("\n{}:", "Cache Efficiency".bold());
+        print_cache_efficiency(&report.totals.cache, "  ");
+    }
+    
+    let subagent = &report.totals.threads.subagent;
+    if subagent.request_count > 0 {
+        println!(
+            "\nSubagents: {} tokens ({:.1}%), ${:.2} of ${:.2} over {} requests",
+            subagent.total_tokens.to_formatted_string(&Locale::en),
+            report.totals.threads.subagent_share(),
+            subagent.cost,
+            report.totals.total_cost,
+            subagent.request_count.to_formatted_string(&Locale::en)
+        );
+    }
+    
+    print_unknown_models(&report.unknown_models, "");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_time;
+    
+    #[test]
+    fn test_build_report_groups_by_period_in_timezone() {
+        let entry = UsageEntry {
+            input_tokens: 100,
+            total_tokens: 100,
+            cost: 1.0,
+            model: "claude-sonnet-4-20250514".to_string(),
+            ..UsageEntry::test_at("2024-01-01T10:00:00Z")
+        };
+        let profiles = vec![
+            ("alpha".to_string(), vec![
+                entry.clone(),
+                // Still Jan 1 in New York
+                UsageEntry {
+                    input_tokens: 200,
+                    total_tokens: 200,
+                    timestamp: test_time("2024-01-02T03:00:00Z"),
+                    ..entry.clone()
+                },
+            ]),
+            ("beta".to_string(), vec![UsageEntry {
+                input_tokens: 400,
+                total_tokens: 400,
+                timestamp: test_time("2024-01-08T10:00:00Z"),
+                ..entry
+            }]),
+        ];
+        let filter = DateFilter {
+            since: None,
+            until: None,
+            zone: ReportZone::new(Some(chrono_tz::America::New_York)),
+        };
+        
+        let daily = build_report(&profiles, ReportPeriod::Daily, &filter, true);
+        assert_eq!(daily.rows.len(), 2);
+        assert_eq!(daily.rows[0].label, "2024-01-01");
+        assert_eq!(daily.rows[0].usage.total_tokens, 300);
+        assert_eq!(daily.rows[0].profiles.len(), 1);
+        assert_eq!(daily.totals.total_tokens, 700);
+        
+        let weekly = build_report(&profiles, ReportPeriod::Weekly, &filter, false);
+        assert_eq!(weekly.rows.len(), 2);
+        assert_eq!(weekly.rows[1].start_date, NaiveDate::from_ymd_opt(2024, 1, 8).unwrap());
+        assert!(weekly.rows[0].profiles.is_empty());
+        
+        let filter = DateFilter {
+            since: NaiveDate::from_ymd_opt(2024, 1, 2),
+            ..filter
+        };
+        let monthly = build_report(&profiles, ReportPeriod::Monthly, &filter, false);
+        assert_eq!(monthly.rows.len(), 1);
+        assert_eq!(monthly.rows[0].label, "2024-01");
+        assert_eq!(monthly.totals.total_tokens, 400);
+    }
+}
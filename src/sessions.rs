@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+use colored::*;
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::models::{format_minutes, SessionBlock, UsageEntry};
+use crate::report::{print_cache_efficiency, ReportZone, UsageSummary};
+
+/// Usage of one Claude Code session
+#[derive(Debug, Serialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub profile: String,
+    pub project: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration_minutes: u64,
+    #[serde(flatten)]
+    pub usage: UsageSummary,
+    /// Start times of the blocks the session's requests fell in
+    pub blocks: Vec<DateTime<Utc>>,
+}
+
+/// One request of a session's timeline
+#[derive(Debug, Serialize)]
+pub struct SessionRequest {
+    pub timestamp: DateTime<Utc>,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub total_tokens: u64,
+    pub cost: f64,
+    /// Made by a Task-tool subagent
+    pub is_sidechain: bool,
+    /// Session cost up to and including this request
+    pub cumulative_cost: f64,
+}
+
+/// A session with its per-request timeline
+#[derive(Debug, Serialize)]
+pub struct SessionDetail {
+    #[serde(flatten)]
+    pub session: SessionSummary,
+    pub requests: Vec<SessionRequest>,
+}
+
+/// Group a profile's entries (sorted by timestamp) into sessions, in order
+/// of their first request, each with its entries
+pub fn build_sessions<'a>(
+    profile: &str,
+    entries: &'a [UsageEntry],
+    blocks: &[SessionBlock],
+) -> Vec<(SessionSummary, Vec<&'a UsageEntry>)> {
+    let mut by_session: HashMap<&str, Vec<&UsageEntry>> = HashMap::new();
+    for entry in entries {
+        let id = entry.session_id.as_deref().unwrap_or("(unknown)");
+        by_session.entry(id).or_default().push(entry);
+    }
+    
+    let mut sessions: Vec<(SessionSummary, Vec<&UsageEntry>)> = by_session
+        .into_iter()
+        .map(|(id, entries)| (summarize_session(id, profile, &entries, blocks), entries))
+        .collect();
+    sessions.sort_by(|a, b| a.0.start_time.cmp(&b.0.start_time).then(a.0.session_id.cmp(&b.0.session_id)));
+    sessions
+}
+
+fn summarize_session(id: &str, profile: &str, entries: &[&UsageEntry], blocks: &[SessionBlock]) -> SessionSummary {
+    let mut usage = UsageSummary::default();
+    for entry in entries {
+        usage.add(entry);
+    }
+    
+    let start_time = entries.first().map_or_else(Utc::now, |entry| entry.timestamp);
+    let end_time = entries.last().map_or(start_time, |entry| entry.timestamp);
+    // Prefer an entry whose exact working directory is known
+    let project = entries
+        .iter()
+        .find(|entry| entry.cwd.as_deref() == Some(entry.project_path()))
+        .or(entries.first())
+        .map_or("(unknown)", |entry| entry.project_path())
+        .to_string();
+    let blocks = blocks
+        .iter()
+        .filter(|block| !block.is_gap)
+        .filter(|block| {
+            entries
+                .iter()
+                .any(|entry| block.start_time <= entry.timestamp && entry.timestamp < block.end_time)
+        })
+        .map(|block| block.start_time)
+        .collect();
+    
+    SessionSummary {
+        session_id: id.to_string(),
+        profile: profile.to_string(),
+        project,
+        start_time,
+        end_time,
+        duration_minutes: (end_time - start_time).num_minutes().max(0) as u64,
+        usage,
+        blocks,
+    }
+}
+
+/// A session with each of its requests and the running cost
+pub fn session_detail(session: SessionSummary, entries: &[&UsageEntry]) -> SessionDetail {
+    let mut cumulative_cost = 0.0;
+    let requests = entries
+        .iter()
+        .map(|entry| {
+            cumulative_cost += entry.cost;
+            SessionRequest {
+                timestamp: entry.timestamp,
+                model: entry.model.clone(),
+                input_tokens: entry.input_tokens,
+                output_tokens: entry.output_tokens,
+                cache_creation_tokens: entry.cache_creation_tokens,
+                cache_read_tokens: entry.cache_read_tokens,
+                total_tokens: entry.total_tokens,
+                cost: entry.cost,
+                is_sidechain: entry.is_sidechain,
+                cumulative_cost,
+            }
+        })
+        .collect();
+    
+    SessionDetail { session, requests }
+}
+
+pub fn print_sessions(sessions: &[SessionSummary], zone: &ReportZone) {
+    println!("{}", "Claude Code Usage - Sessions".bold().green());
+    println!("Timezone: {}\n", zone.name());
+    
+    if sessions.is_empty() {
+        println!("{}", "No sessions in the selected range".yellow());
+        return;
+    }
+    
+    let profile_width = sessions.iter().map(|s| s.profile.len()).chain(["Profile".len()]).max().unwrap_or(7);
+    let project_width = sessions.iter().map(|s| s.project.len()).chain(["Project".len()]).max().unwrap_or(7);
+    let block_lists: Vec<String> = sessions
+        .iter()
+        .map(|session| {
+            session
+                .blocks
+                .iter()
+                .map(|start| zone.format(*start, "%m-%d %H:%M"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+    let blocks_width = block_lists.iter().map(String::len).chain(["Blocks".len()]).max().unwrap_or(6);
+    println!(
+        "{}",
+        format!(
+            "{:<16} {:>8}  {:<8}  {:<profile_width$}  {:<project_width$} {:>8} {:>15} {:>10} {:>9} {:>6}  {:<blocks_width$}  Models",
+            "Start", "Duration", "Session", "Profile", "Project", "Requests", "Tokens", "Cost", "Subagent", "Hit", "Blocks",
+            profile_width = profile_width,
+            project_width = project_width,
+            blocks_width = blocks_width
+        )
+        .bold()
+    );
+    
+    for (session, blocks) in sessions.iter().zip(&block_lists) {
+        println!(
+            "{:<16} {:>8}  {:<8}  {:<profile_width$}  {:<project_width$} {:>8} {:>15} {:>10} {:>9} {:>6}  {:<blocks_width$}  {}",
+            zone.format(session.start_time, "%Y-%m-%d %H:%M"),
+            format_minutes(session.duration_minutes),
+            short_id(&session.session_id),
+            session.profile,
+            session.project,
+            session.usage.entry_count.to_formatted_string(&Locale::en),
+            session.usage.total_tokens.to_formatted_string(&Locale::en),
+            format!("${:.2}", session.usage.total_cost),
+            session.usage.threads.subagent_label(),
+            session.usage.cache.hit_label(),
+            blocks,
+            session.usage.models.iter().cloned().collect::<Vec<_>>().join(", "),
+            profile_width = profile_width,
+            project_width = project_width,
+            blocks_width = blocks_width
+        );
+    }
+}
+
+pub fn print_session_detail(detail: &SessionDetail, zone: &ReportZone) {
+    let session = &detail.session;
+    // Times only need a date when the session runs past midnight
+    let (time_format, time_width) = if zone.date(session.start_time) == zone.date(session.end_time) {
+        ("%H:%M:%S", 8)
+    } else {
+        ("%m-%d %H:%M:%S", 14)
+    };
+    println!("{} {}", "━━━ Session:".bold().blue(), session.session_id.bold().blue());
+    println!("  Profile:  {}", session.profile);
+    println!("  Project:  {}", session.project);
+    println!(
+        "  Time:     {} - {} ({}, {})",
+        zone.format(session.start_time, "%Y-%m-%d %H:%M:%S"),
+        zone.format(session.end_time, time_format),
+        format_minutes(session.duration_minutes),
+        zone.name()
+    );
+    println!("  Models:   {}", session.usage.models.iter().cloned().collect::<Vec<_>>().join(", "));
+    println!(
+        "  Usage:    {} requests, {} tokens, ${:.4}",
+        session.usage.entry_count.to_formatted_string(&Locale::en),
+        session.usage.total_tokens.to_formatted_string(&Locale::en),
+        session.usage.total_cost
+    );
+    let subagent = &session.usage.threads.subagent;
+    if subagent.request_count > 0 {
+        println!(
+            "  Subagent: {} requests, {} tokens ({:.1}%), ${:.4}",
+            subagent.request_count.to_formatted_string(&Locale::en),
+            subagent.total_tokens.to_formatted_string(&Locale::en),
+            session.usage.threads.subagent_share(),
+            subagent.cost
+        );
+    }
+    if session.usage.cache.prompt_tokens > 0 {
+        println!("\n  {}:", "Cache Efficiency".bold());
+        print_cache_efficiency(&session.usage.cache, "    ");
+    }
+    
+    println!(
+        "\n{}",
+        format!(
+            "  {:<time_width$}  {:<28} {:>10} {:>9} {:>11} {:>11} {:>10} {:>10}  Thread",
+            "Time", "Model", "Input", "Output", "Cache+", "Cache-", "Cost", "Total $",
+            time_width = time_width
+        )
+        .bold()
+    );
+    for request in &detail.requests {
+        println!(
+            "  {:<time_width$}  {:<28} {:>10} {:>9} {:>11} {:>11} {:>10} {:>10}  {}",
+            zone.format(request.timestamp, time_format),
+            request.model,
+            request.input_tokens.to_formatted_string(&Locale::en),
+            request.output_tokens.to_formatted_string(&Locale::en),
+            request.cache_creation_tokens.to_formatted_string(&Locale::en),
+            request.cache_read_tokens.to_formatted_string(&Locale::en),
+            format!("${:.4}", request.cost),
+            format!("${:.2}", request.cumulative_cost),
+            if request.is_sidechain { "subagent".cyan() } else { "main".normal() },
+            time_width = time_width
+        );
+    }
+}
+
+/// First 8 characters of a session ID, enough to pick it out
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::identify_session_blocks;
+    use crate::models::test_time;
+    use chrono::Duration;
+    
+    #[test]
+    fn test_build_sessions_and_timeline() {
+        let a = UsageEntry {
+            total_tokens: 100,
+            cost: 1.0,
+            model: "claude-sonnet-4".to_string(),
+            session_id: Some("a".to_string()),
+            ..UsageEntry::test_at("2024-01-01T10:00:00Z")
+        };
+        let entries = vec![
+            a.clone(),
+            UsageEntry {
+                timestamp: test_time("2024-01-01T10:30:00Z"),
+                session_id: Some("b".to_string()),
+                ..a.clone()
+            },
+            UsageEntry { timestamp: test_time("2024-01-01T14:50:00Z"), ..a.clone() },
+            // Next block
+            UsageEntry { timestamp: test_time("2024-01-01T15:10:00Z"), cost: 2.0, ..a },
+        ];
+        let blocks = identify_session_blocks(entries.clone(), Duration::hours(5));
+        
+        let mut sessions = build_sessions("work", &entries, &blocks);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[1].0.blocks.len(), 1);
+        let (a, a_entries) = sessions.remove(0);
+        assert_eq!(a.session_id, "a");
+        assert_eq!(a.duration_minutes, 310);
+        assert_eq!(a.usage.entry_count, 3);
+        assert_eq!(a.blocks.len(), 2);
+        
+        let detail = session_detail(a, &a_entries);
+        assert_eq!(detail.requests.len(), 3);
+        assert_eq!(detail.requests[2].cumulative_cost, 4.0);
+    }
+}
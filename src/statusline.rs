@@ -0,0 +1,193 @@
+use anyhow::Result;
+use chrono::Utc;
+use colored::*;
+use serde::Deserialize;
+use std::io::{self, IsTerminal, Read};
+use std::path::PathBuf;
+
+use crate::models::format_minutes;
+use crate::profiles::{profile_containing, Profile};
+use crate::usage::{load_selected_with_limits, summarize_profile, CheckOptions, ProfileUsage};
+
+/// The parts of Claude Code's statusLine JSON we use
+#[derive(Debug, Default, Deserialize)]
+struct StatusInput {
+    transcript_path: Option<PathBuf>,
+    workspace: Option<StatusWorkspace>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StatusWorkspace {
+    current_dir: Option<PathBuf>,
+}
+
+/// Print a one-line summary of the current profile's active block.
+/// Claude Code pipes session JSON on stdin; the profile comes from
+/// `--profile`, `CLAUDE_CONFIG_DIR` or the transcript path, in that order.
+pub fn run_statusline(profiles: &[Profile], profile_name: Option<&str>, options: CheckOptions) -> Result<()> {
+    let input = read_input();
+    
+    let profile = match profile_name {
+        Some(name) => profiles.iter().find(|(profile, _)| profile == name),
+        None => detect_profile(profiles, &input),
+    };
+    let Some((name, _)) = profile else {
+        println!("{}", "no Claude profile".dimmed());
+        return Ok(());
+    };
+    
+    let usage = load_status_usage(profiles, name, options)?;
+    match usage {
+        Some(Ok(usage)) => println!("{}", format_status_line(&usage)),
+        Some(Err(e)) => println!("{} {}", name, format!("error: {}", e).red()),
+        None => println!("{}", name),
+    }
+    
+    Ok(())
+}
+
+/// Usage of the status line's profile. Only that profile is loaded, through
+/// the parse cache, so this stays fast enough to run on every prompt;
+/// `--limit max-all` needs every profile's history, as in `check`.
+fn load_status_usage(profiles: &[Profile], name: &str, options: CheckOptions) -> Result<Option<Result<ProfileUsage>>> {
+    Ok(load_selected_with_limits(profiles, Some(name), options)?
+        .into_iter()
+        .next()
+        .map(|(name, result)| result.map(|(loaded, limit)| summarize_profile(&name, loaded, limit))))
+}
+
+/// Session JSON from stdin; nothing when run by hand from a terminal
+fn read_input() -> StatusInput {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return StatusInput::default();
+    }
+    
+    let mut raw = String::new();
+    if stdin.lock().read_to_string(&mut raw).is_err() {
+        return StatusInput::default();
+    }
+    serde_json::from_str(&raw).unwrap_or_default()
+}
+
+/// Work out which profile a Claude Code session belongs to
+fn detect_profile<'a>(profiles: &'a [Profile], input: &StatusInput) -> Option<&'a Profile> {
+    let candidates = [
+        std::env::var_os("CLAUDE_CONFIG_DIR").map(PathBuf::from),
+        input.transcript_path.clone(),
+        input.workspace.as_ref().and_then(|w| w.current_dir.clone()),
+    ];
+    
+    candidates
+        .into_iter()
+        .flatten()
+        .find_map(|path| profile_containing(profiles, &path))
+}
+
+fn format_status_line(usage: &ProfileUsage) -> String {
+    let mut parts = vec![usage.name.bold().to_string()];
+    
+    if let Some(reset) = usage.rate_limited_until {
+        parts.push(format!("limited until {}", reset.format("%H:%M UTC")).red().to_string());
+    }
+    
+    let Some(ref block) = usage.active_block else {
+        parts.push("no active block".dimmed().to_string());
+        return parts.join(" │ ");
+    };
+    
+    parts.push(format!("{} tok ${:.2}", compact_number(block.total_tokens), block.total_cost));
+    
+    if let Some(percent) = usage.limit_used_percent {
+        let text = format!("{:.0}%", percent);
+        parts.push(if percent >= 90.0 {
+            text.red().to_string()
+        } else if percent >= 70.0 {
+            text.yellow().to_string()
+        } else {
+            text.green().to_string()
+        });
+    }
+    
+    let remaining = (block.end_time - Utc::now()).num_minutes().max(0) as u64;
+    parts.push(format!("{} left", format_minutes(remaining)));
+    
+    if let Some(time_limit) = block.burn_rate.as_ref().and_then(|br| br.time_until_limit.as_ref()) {
+        // Only worth flagging when the limit comes before the block ends
+        let text = format!("limit in {}", time_limit.human_readable);
+        parts.push(if time_limit.minutes < remaining {
+            text.red().to_string()
+        } else {
+            text.dimmed().to_string()
+        });
+    }
+    
+    parts.join(" │ ")
+}
+
+/// Format a token count as "950", "12.3K" or "1.2M"
+fn compact_number(value: u64) -> String {
+    match value {
+        0..=999 => value.to_string(),
+        // Anything that would round up to "1000.0K" reads as millions
+        1_000..=999_949 => format!("{:.1}K", value as f64 / 1_000.0),
+        _ => format!("{:.1}M", value as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{LimitConfig, LimitMode};
+    use crate::pricing::{CostMode, PricingTable};
+    use std::fs;
+    
+    #[test]
+    fn test_status_usage_learns_limit_across_profiles() {
+        let root = std::env::temp_dir().join(format!("claude-block-checker-status-{}", std::process::id()));
+        let profile = |name: &str, tokens: u64| {
+            let dir = root.join(name).join("projects").join("-repo");
+            fs::create_dir_all(&dir).unwrap();
+            let line = format!(
+                r#"{{"timestamp":"2024-01-01T10:00:00Z","message":{{"usage":{{"input_tokens":{},"output_tokens":0}}}},"model":"claude-sonnet-4"}}"#,
+                tokens
+            );
+            fs::write(dir.join("s.jsonl"), line + "\n").unwrap();
+            (name.to_string(), root.join(name))
+        };
+        let profiles = vec![profile("a", 100), profile("b", 50_000)];
+        let pricing = PricingTable::bundled();
+        let options = CheckOptions {
+            detailed: false,
+            json: false,
+            use_cache: false,
+            limit_mode: LimitMode::MaxAll,
+            cost_mode: CostMode::Auto,
+            strict_pricing: false,
+            limit_defaults: LimitConfig::default(),
+            session_duration: chrono::Duration::hours(5),
+            warn_minutes: 30,
+            critical_minutes: 10,
+            pricing: &pricing,
+        };
+        
+        let observed = |options| {
+            let usage = load_status_usage(&profiles, "a", options).unwrap().unwrap().unwrap();
+            usage.limit.observed_max.unwrap()
+        };
+        assert_eq!(observed(options).profile, "b");
+        assert_eq!(observed(CheckOptions { limit_mode: LimitMode::Max, ..options }).profile, "a");
+        
+        fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn test_compact_number() {
+        assert_eq!(compact_number(950), "950");
+        assert_eq!(compact_number(12_345), "12.3K");
+        assert_eq!(compact_number(999_949), "999.9K");
+        assert_eq!(compact_number(999_950), "1.0M");
+        assert_eq!(compact_number(999_999), "1.0M");
+        assert_eq!(compact_number(148_500_000), "148.5M");
+    }
+}
@@ -0,0 +1,264 @@
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use colored::*;
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::models::{SessionBlock, UsageEntry};
+use crate::projects::{prefer_exact_path, project_key};
+use crate::report::ReportZone;
+
+/// Stands in for the tool of responses that called none
+const NO_TOOLS: &str = "(no tools)";
+
+/// What the tools report is broken down by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToolGrouping {
+    Session,
+    Project,
+    Block,
+}
+
+/// Calls of one tool, with the output tokens and cost of the responses
+/// that made them
+#[derive(Debug, Default, Serialize)]
+pub struct ToolUsage {
+    pub tool: String,
+    pub calls: usize,
+    /// Responses that called the tool at least once
+    pub requests: usize,
+    /// Output tokens attributed to the tool
+    pub output_tokens: u64,
+    /// Cost attributed to the tool
+    pub cost: f64,
+}
+
+/// Tool usage of one session, project or block, or of everything
+#[derive(Debug, Serialize)]
+pub struct ToolGroup {
+    pub group: String,
+    pub output_tokens: u64,
+    pub cost: f64,
+    pub tools: Vec<ToolUsage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolsReport {
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<ToolGrouping>,
+    pub groups: Vec<ToolGroup>,
+}
+
+/// Count tool calls and split each response's output tokens and cost
+/// evenly across the calls it made. `blocks` holds each profile's blocks
+/// and is only needed to group by block.
+pub fn build_tools_report<'a>(
+    entries: impl IntoIterator<Item = (&'a str, &'a UsageEntry)>,
+    group_by: Option<ToolGrouping>,
+    blocks: &HashMap<&str, Vec<SessionBlock>>,
+    zone: &ReportZone,
+    scope: String,
+) -> ToolsReport {
+    let mut groups: HashMap<String, (String, Vec<&UsageEntry>)> = HashMap::new();
+    
+    for (profile, entry) in entries {
+        let (key, label) = match group_by {
+            None => (String::new(), "All".to_string()),
+            Some(ToolGrouping::Session) => {
+                let id = entry.session_id.as_deref().unwrap_or("(unknown)");
+                (format!("{}/{}", profile, id), id.to_string())
+            }
+            Some(ToolGrouping::Project) => (project_key(entry).to_string(), entry.project_path().to_string()),
+            Some(ToolGrouping::Block) => {
+                let start = blocks.get(profile).and_then(|blocks| block_start(blocks, entry.timestamp));
+                let start = start.map_or_else(|| "(no block)".to_string(), |start| zone.format(start, "%Y-%m-%d %H:%M"));
+                (format!("{}/{}", profile, start), format!("{} {}", profile, start))
+            }
+        };
+        let group = groups.entry(key).or_insert_with(|| (label, Vec::new()));
+        if group_by == Some(ToolGrouping::Project) {
+            prefer_exact_path(&mut group.0, entry);
+        }
+        group.1.push(entry);
+    }
+    
+    let mut groups: Vec<ToolGroup> = groups
+        .into_values()
+        .map(|(group, entries)| ToolGroup {
+            group,
+            output_tokens: entries.iter().map(|entry| entry.output_tokens).sum(),
+            cost: entries.iter().map(|entry| entry.cost).sum(),
+            tools: tally_tools(&entries),
+        })
+        .collect();
+    groups.sort_by(|a, b| b.output_tokens.cmp(&a.output_tokens).then(a.group.cmp(&b.group)));
+    
+    ToolsReport { scope, group_by, groups }
+}
+
+/// Start of the block a timestamp falls in
+fn block_start(blocks: &[SessionBlock], timestamp: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    blocks
+        .iter()
+        .find(|block| !block.is_gap && block.start_time <= timestamp && timestamp < block.end_time)
+        .map(|block| block.start_time)
+}
+
+/// Tool usage of a set of entries, most output tokens first
+fn tally_tools(entries: &[&UsageEntry]) -> Vec<ToolUsage> {
+    let mut tools: HashMap<&str, ToolUsage> = HashMap::new();
+    
+    for entry in entries {
+        let calls: Vec<&str> = if entry.tools.is_empty() {
+            vec![NO_TOOLS]
+        } else {
+            entry.tools.iter().map(|call| call.name.as_str()).collect()
+        };
+        let count = calls.len() as u64;
+        
+        for (index, name) in calls.iter().enumerate() {
+            let tool = tools.entry(name).or_insert_with(|| ToolUsage {
+                tool: name.to_string(),
+                ..Default::default()
+            });
+            // Spread the remainder over the first calls so no token is lost
+            let extra = ((index as u64) < entry.output_tokens % count) as u64;
+            tool.output_tokens += entry.output_tokens / count + extra;
+            tool.cost += entry.cost / count as f64;
+            if !calls[..index].contains(name) {
+                tool.requests += 1;
+            }
+            if *name != NO_TOOLS {
+                tool.calls += 1;
+            }
+        }
+    }
+    
+    let mut tools: Vec<ToolUsage> = tools.into_values().collect();
+    tools.sort_by(|a, b| {
+        b.output_tokens
+            .cmp(&a.output_tokens)
+            .then(b.calls.cmp(&a.calls))
+            .then(a.tool.cmp(&b.tool))
+    });
+    tools
+}
+
+pub fn print_tools_report(report: &ToolsReport) {
+    println!("{}", "Claude Code Usage - Tools".bold().green());
+    println!("Scope: {}", report.scope);
+    println!("{}\n", "Each response's output tokens and cost are split evenly across its tool calls".dimmed());
+    
+    if report.groups.is_empty() {
+        println!("{}", "No usage in the selected range".yellow());
+        return;
+    }
+    
+    let width = report
+        .groups
+        .iter()
+        .flat_map(|group| group.tools.iter().map(|tool| tool.tool.len()))
+        .chain(["Tool".len()])
+        .max()
+        .unwrap_or(4);
+    
+    for group in &report.groups {
+        if let Some(group_by) = report.group_by {
+            let kind = match group_by {
+                ToolGrouping::Session => "Session:",
+                ToolGrouping::Project => "Project:",
+                ToolGrouping::Block => "Block:",
+            };
+            println!(
+                "{} {} {}",
+                "━━━".bold().blue(),
+                kind.bold().blue(),
+                group.group.bold().blue()
+            );
+        }
+        println!(
+            "{}",
+            format!(
+                "  {:<width$} {:>7} {:>9} {:>15} {:>7} {:>11}",
+                "Tool", "Calls", "Requests", "Output tokens", "Share", "Cost",
+                width = width
+            )
+            .bold()
+        );
+        for tool in &group.tools {
+            let share = if group.output_tokens > 0 {
+                tool.output_tokens as f64 / group.output_tokens as f64 * 100.0
+            } else {
+                0.0
+            };
+            let name = if tool.tool == NO_TOOLS {
+                tool.tool.dimmed()
+            } else {
+                tool.tool.normal()
+            };
+            println!(
+                "  {:<width$} {:>7} {:>9} {:>15} {:>6.1}% {:>11}",
+                name,
+                tool.calls.to_formatted_string(&Locale::en),
+                tool.requests.to_formatted_string(&Locale::en),
+                tool.output_tokens.to_formatted_string(&Locale::en),
+                share,
+                format!("${:.2}", tool.cost),
+                width = width
+            );
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ToolCall;
+    
+    #[test]
+    fn test_tool_calls_attributed() {
+        let calls = |names: &[&str]| -> Vec<ToolCall> {
+            names.iter().map(|name| ToolCall { id: None, name: name.to_string() }).collect()
+        };
+        let a = UsageEntry {
+            session_id: Some("a".to_string()),
+            output_tokens: 100,
+            cost: 1.0,
+            tools: calls(&["Bash", "Read", "Read"]),
+            ..UsageEntry::test_at("2024-01-01T10:00:00Z")
+        };
+        let entries = [
+            ("work", a.clone()),
+            ("work", UsageEntry { output_tokens: 50, tools: calls(&["Bash"]), ..a.clone() }),
+            ("work", UsageEntry {
+                session_id: Some("b".to_string()),
+                output_tokens: 30,
+                tools: Vec::new(),
+                ..a
+            }),
+        ];
+        let iter = || entries.iter().map(|(profile, entry)| (*profile, entry));
+        let zone = ReportZone::new(None);
+        
+        let report = build_tools_report(iter(), None, &HashMap::new(), &zone, "test".to_string());
+        let tools = &report.groups[0].tools;
+        assert_eq!(tools[0].tool, "Bash");
+        assert_eq!(tools[0].calls, 2);
+        assert_eq!(tools[0].requests, 2);
+        assert_eq!(tools[0].output_tokens, 84);
+        let read = tools.iter().find(|tool| tool.tool == "Read").unwrap();
+        assert_eq!((read.calls, read.requests, read.output_tokens), (2, 1, 66));
+        let none = tools.iter().find(|tool| tool.tool == NO_TOOLS).unwrap();
+        assert_eq!((none.calls, none.requests), (0, 1));
+        assert_eq!(tools.iter().map(|tool| tool.output_tokens).sum::<u64>(), 180);
+        
+        let report = build_tools_report(iter(), Some(ToolGrouping::Session), &HashMap::new(), &zone, "test".to_string());
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups[0].group, "a");
+        assert_eq!(report.groups[0].output_tokens, 150);
+    }
+}
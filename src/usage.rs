@@ -0,0 +1,307 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use colored::*;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::blocks::{attach_limit_events, calculate_burn_rate, identify_session_blocks};
+use crate::limits::{load_profile_limit, max_observed_block, LimitConfig, LimitMode, UsageLimit};
+use crate::models::{LimitEvent, SessionBlock};
+use crate::parser::{load_usage_entries, LoadedUsage};
+use crate::pricing::{require_known_prices, unknown_models, CostMode, PricingTable, UnknownModel, UnknownPricing};
+use crate::profiles::{select_profiles, Profile};
+
+#[derive(Debug, Serialize)]
+pub struct ProfileUsage {
+    pub name: String,
+    pub active_block: Option<SessionBlock>,
+    pub total_tokens: u64,
+    pub total_cost: f64,
+    pub models_used: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes_until_limit: Option<u64>,
+    pub limit: UsageLimit,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_used_percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limited_until: Option<DateTime<Utc>>,
+    pub duplicates_removed: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_models: Vec<UnknownModel>,
+}
+
+impl ProfileUsage {
+    /// Usage for a profile without an active block
+    pub fn empty(name: &str, limit: UsageLimit) -> Self {
+        Self {
+            name: name.to_string(),
+            active_block: None,
+            total_tokens: 0,
+            total_cost: 0.0,
+            models_used: Vec::new(),
+            minutes_until_limit: None,
+            limit,
+            limit_used_percent: None,
+            rate_limited_until: None,
+            duplicates_removed: 0,
+            unknown_models: Vec::new(),
+        }
+    }
+}
+
+/// Options shared by the block checking commands
+#[derive(Debug, Clone, Copy)]
+pub struct CheckOptions<'a> {
+    pub detailed: bool,
+    pub json: bool,
+    pub use_cache: bool,
+    pub limit_mode: LimitMode,
+    pub cost_mode: CostMode,
+    /// Fail on models that only a fallback rule can price
+    pub strict_pricing: bool,
+    /// Limits for profiles without their own config file
+    pub limit_defaults: LimitConfig,
+    pub session_duration: Duration,
+    /// Time until limit thresholds for yellow and red
+    pub warn_minutes: u64,
+    pub critical_minutes: u64,
+    pub pricing: &'a PricingTable,
+}
+
+/// A profile's blocks and resolved limit, or why they could not be loaded
+pub type ProfileWithLimit = (String, Result<(ProfileBlocks, UsageLimit)>);
+
+/// Session blocks and parse statistics for one profile
+#[derive(Debug, Default)]
+pub struct ProfileBlocks {
+    pub blocks: Vec<SessionBlock>,
+    pub duplicates_removed: usize,
+    pub limit_events: Vec<LimitEvent>,
+    /// Models in the profile's history that only a fallback could price
+    pub unknown_models: Vec<UnknownModel>,
+}
+
+/// Load the usage entries of several profiles in parallel. Profiles that
+/// fail to load are reported and left out, except that a model
+/// `--strict-pricing` could not price fails the whole command.
+pub fn load_profiles_entries(profiles: &[Profile], options: CheckOptions) -> Result<Vec<(String, LoadedUsage)>> {
+    let mut loaded_profiles = Vec::new();
+    let results: Vec<_> = profiles
+        .par_iter()
+        .map(|(name, path)| (name, load_profile_entries(path, options)))
+        .collect();
+    
+    for (name, result) in results {
+        match result {
+            Ok(loaded) => loaded_profiles.push((name.clone(), loaded)),
+            Err(e) if e.is::<UnknownPricing>() => return Err(e.context(format!("Profile {}", name))),
+            Err(e) => eprintln!("{}", format!("Profile {}: {}", name, e).red()),
+        }
+    }
+    
+    Ok(loaded_profiles)
+}
+
+fn load_profile_entries(profile_path: &Path, options: CheckOptions) -> Result<LoadedUsage> {
+    let projects_dir = profile_path.join("projects");
+    
+    if !projects_dir.exists() {
+        return Ok(LoadedUsage::default());
+    }
+    
+    let mut loaded = load_usage_entries(&projects_dir, options.use_cache)?;
+    options.pricing.apply(&mut loaded.entries, options.cost_mode);
+    if options.strict_pricing {
+        require_known_prices(&unknown_models(options.pricing, &loaded.entries, options.cost_mode))?;
+    }
+    Ok(loaded)
+}
+
+/// Load several profiles' blocks in parallel, keeping their order, and
+/// resolve the limit each profile is measured against. Profiles that fail
+/// to load carry their error, except that a model `--strict-pricing` could
+/// not price fails the whole command.
+pub fn load_profiles_with_limits(
+    profiles: &[Profile],
+    options: CheckOptions,
+) -> Result<Vec<ProfileWithLimit>> {
+    let loaded: Vec<(&String, &PathBuf, Result<ProfileBlocks>)> = profiles
+        .par_iter()
+        .map(|(name, path)| (name, path, load_profile_blocks(path, options)))
+        .collect();
+    
+    let global_max = if options.limit_mode == LimitMode::MaxAll {
+        loaded
+            .iter()
+            .filter_map(|(name, _, result)| {
+                result.as_ref().ok().and_then(|loaded| max_observed_block(name, &loaded.blocks))
+            })
+            .max_by_key(|observed| (observed.total_tokens, observed.hit_limit))
+    } else {
+        None
+    };
+    
+    loaded
+        .into_iter()
+        .map(|(name, path, result)| {
+            let with_limit = result.and_then(|loaded| {
+                let plan_limit = load_profile_limit(path, &options.limit_defaults)?;
+                let limit = match options.limit_mode {
+                    LimitMode::Plan => plan_limit,
+                    LimitMode::Max => plan_limit.with_observed_max(max_observed_block(name, &loaded.blocks)),
+                    LimitMode::MaxAll => plan_limit.with_observed_max(global_max.clone()),
+                };
+                Ok((loaded, limit))
+            });
+            match with_limit {
+                Err(e) if e.is::<UnknownPricing>() => Err(e.context(format!("Profile {}", name))),
+                with_limit => Ok((name.clone(), with_limit)),
+            }
+        })
+        .collect()
+}
+
+/// Load the named profile, or every profile, with resolved limits.
+/// Learning the limit across profiles needs every profile's history, so
+/// `--limit max-all` always loads them all.
+pub fn load_selected_with_limits(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    options: CheckOptions,
+) -> Result<Vec<ProfileWithLimit>> {
+    let selected = select_profiles(profiles, profile_name)?;
+    let to_load = if options.limit_mode == LimitMode::MaxAll {
+        profiles
+    } else {
+        &selected
+    };
+    
+    Ok(load_profiles_with_limits(to_load, options)?
+        .into_iter()
+        .filter(|(name, _)| selected.iter().any(|(selected, _)| selected == name))
+        .collect())
+}
+
+fn load_profile_blocks(profile_path: &Path, options: CheckOptions) -> Result<ProfileBlocks> {
+    // Load all usage entries
+    let loaded = load_profile_entries(profile_path, options)?;
+    
+    let unknown_models = unknown_models(options.pricing, &loaded.entries, options.cost_mode);
+    
+    // Identify session blocks
+    let mut blocks = identify_session_blocks(loaded.entries, options.session_duration);
+    attach_limit_events(&mut blocks, &loaded.limit_events);
+    
+    Ok(ProfileBlocks {
+        blocks,
+        duplicates_removed: loaded.duplicates_removed,
+        limit_events: loaded.limit_events,
+        unknown_models,
+    })
+}
+
+/// Reduce a profile's blocks to its active block and projections
+pub fn summarize_profile(profile_name: &str, loaded: ProfileBlocks, limit: UsageLimit) -> ProfileUsage {
+    let now = Utc::now();
+    let duplicates_removed = loaded.duplicates_removed;
+    let unknown_models = loaded.unknown_models;
+    
+    // A limit message whose reset time is still ahead means the profile
+    // cannot be used right now, whatever the block totals say
+    let rate_limited_until = loaded.limit_events.iter()
+        .filter_map(|event| event.reset_time)
+        .filter(|reset| *reset > now)
+        .max();
+    
+    // Find active block
+    let mut active_block = loaded.blocks.into_iter()
+        .find(|block| block.is_active);
+    
+    // Always calculate burn rate to determine time until limit
+    if let Some(ref mut block) = active_block {
+        block.burn_rate = calculate_burn_rate(block, &limit, now);
+    }
+    
+    if let Some(ref block) = active_block {
+        let minutes_until_limit = block.burn_rate.as_ref()
+            .and_then(|br| br.time_until_limit.as_ref())
+            .map(|tul| tul.minutes);
+            
+        ProfileUsage {
+            name: profile_name.to_string(),
+            total_tokens: block.total_tokens,
+            total_cost: block.total_cost,
+            models_used: block.models.clone(),
+            minutes_until_limit,
+            limit_used_percent: limit.percent_used(block),
+            limit,
+            active_block,
+            rate_limited_until,
+            duplicates_removed,
+            unknown_models,
+        }
+    } else {
+        ProfileUsage {
+            rate_limited_until,
+            duplicates_removed,
+            unknown_models,
+            ..ProfileUsage::empty(profile_name, limit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::PROFILE_CONFIG_FILE;
+    use std::fs;
+    
+    #[test]
+    fn test_strict_pricing_fails_only_on_unknown_prices() {
+        let root = std::env::temp_dir().join(format!("claude-block-checker-strict-{}", std::process::id()));
+        let profile = |name: &str, model: &str| {
+            let dir = root.join(name).join("projects").join("-repo");
+            fs::create_dir_all(&dir).unwrap();
+            let line = format!(
+                r#"{{"timestamp":"2024-01-01T10:00:00Z","message":{{"usage":{{"input_tokens":100,"output_tokens":0}}}},"model":"{}"}}"#,
+                model
+            );
+            fs::write(dir.join("s.jsonl"), line + "\n").unwrap();
+            (name.to_string(), root.join(name))
+        };
+        let known = profile("known", "claude-sonnet-4");
+        let broken = profile("broken", "claude-sonnet-4");
+        fs::write(broken.1.join(PROFILE_CONFIG_FILE), "plan = ").unwrap();
+        let unknown = profile("unknown", "gpt-4o");
+        
+        let pricing = PricingTable::bundled();
+        let options = CheckOptions {
+            detailed: false,
+            json: false,
+            use_cache: false,
+            limit_mode: LimitMode::Plan,
+            cost_mode: CostMode::Auto,
+            strict_pricing: true,
+            limit_defaults: LimitConfig::default(),
+            session_duration: Duration::hours(5),
+            warn_minutes: 30,
+            critical_minutes: 10,
+            pricing: &pricing,
+        };
+        
+        // A broken profile config is still reported per profile
+        let loaded = load_profiles_with_limits(&[known.clone(), broken], options).unwrap();
+        assert!(loaded[0].1.is_ok());
+        assert!(loaded[1].1.is_err());
+        
+        let err = load_profiles_with_limits(&[known.clone(), unknown.clone()], options).unwrap_err();
+        assert!(err.is::<UnknownPricing>());
+        assert!(load_profiles_entries(&[known.clone(), unknown.clone()], options).is_err());
+        
+        let lenient = CheckOptions { strict_pricing: false, ..options };
+        assert_eq!(load_profiles_entries(&[known, unknown], lenient).unwrap().len(), 2);
+        
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use colored::*;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    terminal::{self, ClearType},
+};
+use num_format::{Locale, ToFormattedString};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::models::format_minutes;
+use crate::profiles::Profile;
+use crate::usage::{load_selected_with_limits, summarize_profile, CheckOptions, ProfileUsage};
+
+/// Width of the per-profile progress bars, in characters
+const BAR_WIDTH: usize = 40;
+
+/// Switches to the alternate screen in raw mode and restores the terminal
+/// when dropped, including when the dashboard bails out with an error
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Redraw the multi-profile dashboard until the user presses `q`.
+/// Usage is reloaded every `interval`; the parse cache keeps each reload
+/// down to the files that changed. The countdown is redrawn every second.
+pub fn run_watch(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    interval: Duration,
+    options: CheckOptions,
+) -> Result<()> {
+    // Fail on a bad profile name before taking over the screen
+    let mut usages = load_usages(profiles, profile_name, options)?;
+    let mut updated_at = Utc::now();
+    let mut last_refresh = Instant::now();
+    let mut force_refresh = false;
+    
+    let _guard = TerminalGuard::enter()?;
+    
+    loop {
+        if force_refresh || last_refresh.elapsed() >= interval {
+            usages = load_usages(profiles, profile_name, options)?;
+            updated_at = Utc::now();
+            last_refresh = Instant::now();
+            force_refresh = false;
+        }
+        
+        let next_refresh = interval.saturating_sub(last_refresh.elapsed());
+        draw(&render(&usages, updated_at, next_refresh, Utc::now()))?;
+        
+        if event::poll(Duration::from_secs(1))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if is_quit(&key) {
+                    break;
+                }
+                force_refresh = key.code == KeyCode::Char('r');
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Raw mode swallows Ctrl+C, so treat it like `q`
+fn is_quit(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+/// Current usage of every watched profile, or the error that stopped it
+fn load_usages(
+    profiles: &[Profile],
+    profile_name: Option<&str>,
+    options: CheckOptions,
+) -> Result<Vec<(String, Result<ProfileUsage>)>> {
+    Ok(load_selected_with_limits(profiles, profile_name, options)?
+        .into_iter()
+        .map(|(name, result)| {
+            let usage = result.map(|(loaded, limit)| summarize_profile(&name, loaded, limit));
+            (name, usage)
+        })
+        .collect())
+}
+
+/// Replace the screen contents; raw mode needs explicit carriage returns
+fn draw(lines: &[String]) -> Result<()> {
+    let mut stdout = io::stdout();
+    queue!(stdout, cursor::MoveTo(0, 0), terminal::Clear(ClearType::All))?;
+    for line in lines {
+        write!(stdout, "{}\r\n", line)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Lines of one dashboard frame as of `now`
+fn render(
+    usages: &[(String, Result<ProfileUsage>)],
+    updated_at: DateTime<Utc>,
+    next_refresh: Duration,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let mut lines = vec![
+        "Claude Code Usage - Live".bold().green().to_string(),
+        format!(
+            "Updated {}, next refresh in {}s   {}",
+            updated_at.format("%H:%M:%S UTC"),
+            next_refresh.as_secs(),
+            "q: quit  r: refresh".dimmed()
+        ),
+        String::new(),
+    ];
+    
+    for (name, usage) in usages {
+        let usage = match usage {
+            Ok(usage) => usage,
+            Err(e) => {
+                lines.push(format!("{} {}", format!("━━━ {}", name).bold().blue(), format!("(Error: {})", e).red()));
+                lines.push(String::new());
+                continue;
+            }
+        };
+        
+        lines.push(format!(
+            "{} {}",
+            format!("━━━ {}", usage.name).bold().blue(),
+            format!("({} limit)", usage.limit.label()).dimmed()
+        ));
+        
+        if let Some(reset) = usage.rate_limited_until {
+            lines.push(format!("  {} Rate limited until {}", "✖".red(), reset.format("%H:%M UTC").to_string().red().bold()));
+        }
+        
+        let Some(ref block) = usage.active_block else {
+            lines.push(format!("  {}", "No active block".yellow()));
+            lines.push(String::new());
+            continue;
+        };
+        
+        let percent = usage.limit_used_percent.unwrap_or(0.0);
+        lines.push(format!("  {} {:>5.1}%", progress_bar(percent), percent));
+        lines.push(format!(
+            "  Tokens: {}   Cost: ${:.4}",
+            block.total_tokens.to_formatted_string(&Locale::en),
+            block.total_cost
+        ));
+        
+        let remaining = (block.end_time - now).num_seconds().max(0) as u64;
+        let mut status = format!(
+            "  Block ends in {} {:02}s",
+            format_minutes(remaining / 60),
+            remaining % 60
+        );
+        if let Some(ref burn_rate) = block.burn_rate {
+            status.push_str(&format!(
+                "   Burn: {} tok/min, ${:.2}/h",
+                burn_rate.tokens_per_minute.to_formatted_string(&Locale::en),
+                burn_rate.cost_per_hour
+            ));
+            if let Some(ref time_limit) = burn_rate.time_until_limit {
+                // Projections are from the last refresh, so count them down too
+                let elapsed = (now - updated_at).num_minutes().max(0) as u64;
+                status.push_str(&format!(
+                    "   Limit in: {}",
+                    format_minutes(time_limit.minutes.saturating_sub(elapsed))
+                ));
+            }
+        }
+        lines.push(status);
+        lines.push(String::new());
+    }
+    
+    lines
+}
+
+fn progress_bar(percent: f64) -> String {
+    let filled = ((percent / 100.0).clamp(0.0, 1.0) * BAR_WIDTH as f64).round() as usize;
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+    let bar = if percent >= 90.0 {
+        bar.red()
+    } else if percent >= 70.0 {
+        bar.yellow()
+    } else {
+        bar.green()
+    };
+    format!("[{}]", bar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::identify_session_blocks_at;
+    use crate::limits::UsageLimit;
+    use crate::models::{test_time, BurnRate, TimeUntilLimit, UsageEntry};
+    use anyhow::anyhow;
+    
+    #[test]
+    fn test_render_frame() {
+        let now = test_time("2024-01-01T12:30:15Z");
+        let entries = vec![UsageEntry {
+            total_tokens: 1_500,
+            cost: 0.25,
+            ..UsageEntry::test_at("2024-01-01T10:15:00Z")
+        }];
+        let mut block = identify_session_blocks_at(entries, chrono::Duration::hours(5), now).remove(0);
+        block.burn_rate = Some(BurnRate {
+            elapsed_minutes: 150,
+            tokens_per_minute: 10,
+            cost_per_hour: 0.1,
+            projected_tokens: 3_000,
+            projected_cost: 0.5,
+            time_until_limit: Some(TimeUntilLimit::from_minutes(90)),
+        });
+        let active = ProfileUsage {
+            active_block: Some(block),
+            limit_used_percent: Some(50.0),
+            ..ProfileUsage::empty("work", UsageLimit::default())
+        };
+        let usages = vec![
+            ("work".to_string(), Ok(active)),
+            ("idle".to_string(), Ok(ProfileUsage::empty("idle", UsageLimit::default()))),
+            ("broken".to_string(), Err(anyhow!("bad config"))),
+        ];
+        
+        // Drawn ten minutes after the last refresh
+        let updated_at = test_time("2024-01-01T12:20:15Z");
+        let lines = render(&usages, updated_at, Duration::from_secs(4), now);
+        assert!(lines[1].starts_with("Updated 12:20:15 UTC, next refresh in 4s"));
+        let frame = lines.join("\n");
+        assert!(frame.contains("Tokens: 1,500   Cost: $0.2500"));
+        assert!(frame.contains("Block ends in 2h 29m 45s"));
+        assert!(frame.contains("Limit in: 1h 20m"));
+        assert!(frame.contains("No active block"));
+        assert!(frame.contains("(Error: bad config)"));
+    }
+    
+    #[test]
+    fn test_progress_bar_fill() {
+        let filled = |percent| progress_bar(percent).matches('█').count();
+        assert_eq!(filled(0.0), 0);
+        assert_eq!(filled(50.0), BAR_WIDTH / 2);
+        assert_eq!(filled(150.0), BAR_WIDTH);
+        assert_eq!(progress_bar(-5.0).matches('░').count(), BAR_WIDTH);
+    }
+    
+    #[test]
+    fn test_is_quit() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert!(is_quit(&key(KeyCode::Char('q'), KeyModifiers::NONE)));
+        assert!(is_quit(&key(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(is_quit(&key(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(!is_quit(&key(KeyCode::Char('c'), KeyModifiers::NONE)));
+        assert!(!is_quit(&key(KeyCode::Char('r'), KeyModifiers::NONE)));
+    }
+}